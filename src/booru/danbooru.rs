@@ -0,0 +1,333 @@
+use super::{resolve_alias, Image, ImageSource, Query, Result, SearchResult, Sort, Tag};
+use crate::config::ImageSourceConfig;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+const PER_PAGE: &str = "50";
+const NSFW_RATINGS: &[&str] = &["q", "e"];
+
+#[derive(Debug, Deserialize)]
+struct CountResponse {
+    counts: Counts,
+}
+
+#[derive(Debug, Deserialize)]
+struct Counts {
+    posts: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IqdbMatch {
+    post: DanbooruPost,
+}
+
+#[derive(Debug, Deserialize)]
+struct DanbooruPost {
+    id: u32,
+    #[serde(default)]
+    tag_string: String,
+    rating: Option<String>,
+    created_at: Option<String>,
+    // missing on posts that need an account to view
+    file_url: Option<String>,
+    large_file_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DanbooruTag {
+    name: String,
+    post_count: u64,
+}
+
+// tags are separated by spaces, so tag names use underscores instead
+fn tag_name(tag: &str) -> String {
+    tag.trim().replace(' ', "_")
+}
+
+#[derive(Debug)]
+pub struct Danbooru {
+    name: String,
+    base_url: Url,
+    aliases: HashMap<String, HashSet<String>>,
+}
+
+impl Danbooru {
+    pub fn new(name: String, config: &ImageSourceConfig) -> Self {
+        Self {
+            name,
+            base_url: config.url.clone(),
+            aliases: config.aliases.clone(),
+        }
+    }
+
+    fn convert(&self, post: DanbooruPost) -> Result<Option<Image>> {
+        let image_url = match post.large_file_url.or(post.file_url) {
+            Some(image_url) => image_url,
+            None => return Ok(None),
+        };
+        Ok(Some(Image {
+            url: self
+                .base_url
+                .join(&format!("posts/{}", post.id))?
+                .as_str()
+                .to_owned(),
+            id: post.id,
+            image_url,
+            name: String::new(),
+            description: String::new(),
+            tags: post
+                .tag_string
+                .split_whitespace()
+                .map(|tag| tag.replace('_', " "))
+                .collect(),
+            first_seen_at: post.created_at,
+        }))
+    }
+
+    fn convert_all(&self, posts: Vec<DanbooruPost>) -> Result<Vec<Image>> {
+        let mut images = Vec::new();
+        for post in posts {
+            if let Some(image) = self.convert(post)? {
+                images.push(image);
+            }
+        }
+        Ok(images)
+    }
+}
+
+impl ImageSource for Danbooru {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn search(&self, query: &Query, nsfw: bool) -> Result<SearchResult> {
+        let mut terms: Vec<String> = query
+            .tags
+            .iter()
+            .map(|tag| tag_name(resolve_alias(&self.aliases, tag)))
+            .chain(
+                query
+                    .excluded
+                    .iter()
+                    .map(|tag| format!("-{}", tag_name(resolve_alias(&self.aliases, tag)))),
+            )
+            .collect();
+        if let Some(score) = query.min_score {
+            terms.push(format!("score:>={}", score));
+        }
+        if !nsfw {
+            terms.push(format!("-rating:{}", NSFW_RATINGS.join(",")));
+        }
+        let tags = terms.join(" ");
+
+        let mut params = vec![
+            ("limit", PER_PAGE.to_owned()),
+            (
+                "tags",
+                if query.sort == Sort::Top {
+                    format!("{} order:score", tags)
+                } else {
+                    tags.clone()
+                },
+            ),
+        ];
+        if query.sort == Sort::Random {
+            params.push(("random", "true".to_owned()));
+        }
+        let url = Url::parse_with_params(self.base_url.join("posts.json")?.as_ref(), &params)?;
+        trace!("Search URL: {}", url);
+        let posts: Vec<DanbooruPost> = reqwest::blocking::get(url)?.json()?;
+
+        let url = Url::parse_with_params(
+            self.base_url.join("counts/posts.json")?.as_ref(),
+            &[("tags", tags)],
+        )?;
+        trace!("Count URL: {}", url);
+        let count: CountResponse = reqwest::blocking::get(url)?.json()?;
+
+        let images = self.convert_all(posts)?;
+        Ok(SearchResult {
+            total: count.counts.posts.unwrap_or(images.len()),
+            images,
+        })
+    }
+
+    fn tags(&self, prefix: &str) -> Result<Vec<Tag>> {
+        let prefix: String = tag_name(&prefix.to_lowercase())
+            .chars()
+            .filter(|c| !matches!(c, '*' | ','))
+            .collect();
+        let url = Url::parse_with_params(
+            self.base_url.join("tags.json")?.as_ref(),
+            &[
+                ("limit", PER_PAGE.to_owned()),
+                ("search[name_matches]", format!("{}*", prefix)),
+                ("search[order]", "count".to_owned()),
+            ],
+        )?;
+        trace!("Tag search URL: {}", url);
+
+        let tags: Vec<DanbooruTag> = reqwest::blocking::get(url)?.json()?;
+        let mut tags: Vec<_> = tags
+            .into_iter()
+            .map(|tag| Tag {
+                name: tag.name.replace('_', " "),
+                images: tag.post_count,
+                aliased_to: None,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.images.cmp(&a.images).then_with(|| a.name.cmp(&b.name)));
+        Ok(tags)
+    }
+
+    fn reverse_search(&self, image_url: &str, nsfw: bool) -> Result<Vec<Image>> {
+        let url = Url::parse_with_params(
+            self.base_url.join("iqdb_queries.json")?.as_ref(),
+            &[("url", image_url)],
+        )?;
+        trace!("Reverse search URL: {}", url);
+
+        let matches: Vec<IqdbMatch> = reqwest::blocking::get(url)?.error_for_status()?.json()?;
+        self.convert_all(
+            matches
+                .into_iter()
+                .map(|found| found.post)
+                .filter(|post| {
+                    nsfw || !post
+                        .rating
+                        .as_deref()
+                        .map_or(false, |rating| NSFW_RATINGS.contains(&rating))
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{booru::test_server, config::BooruApi};
+    use maplit::{hashmap, hashset};
+
+    fn source(url: Url) -> Danbooru {
+        Danbooru::new(
+            "danbooru".to_owned(),
+            &ImageSourceConfig {
+                api: BooruApi::Danbooru,
+                url,
+                filter: None,
+                nsfw_filter: None,
+                aliases: hashmap! {
+                    "twilight sparkle".to_owned() => hashset! {"twi".to_owned()},
+                },
+            },
+        )
+    }
+
+    fn query_pairs(request: &str) -> HashMap<String, String> {
+        Url::parse("http://localhost")
+            .unwrap()
+            .join(request)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[test]
+    fn search_builds_tags() {
+        let (url, requests) = test_server::serve_all(vec![
+            include_str!("fixtures/danbooru_posts.json"),
+            include_str!("fixtures/danbooru_counts.json"),
+        ]);
+        source(url)
+            .search(
+                &Query::parse("twi, -pinkie pie, score.gt:9, sort:top").unwrap(),
+                false,
+            )
+            .unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/posts.json?"));
+        let query = query_pairs(&request);
+        assert_eq!(
+            query["tags"],
+            "twilight_sparkle -pinkie_pie score:>=10 -rating:q,e order:score"
+        );
+        assert!(!query.contains_key("random"));
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/counts/posts.json?"));
+        assert_eq!(
+            query_pairs(&request)["tags"],
+            "twilight_sparkle -pinkie_pie score:>=10 -rating:q,e"
+        );
+    }
+
+    #[test]
+    fn search_parses_posts() {
+        let (url, requests) = test_server::serve_all(vec![
+            include_str!("fixtures/danbooru_posts.json"),
+            include_str!("fixtures/danbooru_counts.json"),
+        ]);
+        let result = source(url.clone())
+            .search(&Query::parse("twi").unwrap(), true)
+            .unwrap();
+
+        let query = query_pairs(&requests.recv().unwrap());
+        assert_eq!(query["tags"], "twilight_sparkle");
+        assert_eq!(query["random"], "true");
+
+        assert_eq!(result.total, 8123);
+        // the third post has no file to show
+        assert_eq!(result.images.len(), 2);
+        let first = &result.images[0];
+        assert_eq!(first.id, 5_021_877);
+        assert_eq!(first.url, url.join("posts/5021877").unwrap().as_str());
+        assert_eq!(
+            first.image_url,
+            "https://cdn.donmai.us/sample/6a/2f/sample-6a2f.jpg"
+        );
+        assert!(first.tags.contains(&"my little pony".to_owned()));
+        assert_eq!(
+            result.images[1].image_url,
+            "https://cdn.donmai.us/original/1b/3c/1b3c.png"
+        );
+    }
+
+    #[test]
+    fn tags_search_by_prefix() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/danbooru_tags.json"));
+        let tags = source(url).tags(" Twilight Sp*").unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/tags.json?"));
+        assert_eq!(
+            query_pairs(&request)["search[name_matches]"],
+            "twilight_sp*"
+        );
+
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["twilight sparkle", "twilight sparkle (alicorn)"]
+        );
+        assert_eq!(tags[0].images, 9830);
+    }
+
+    #[test]
+    fn reverse_search_filters_ratings() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/danbooru_iqdb.json"));
+        let images = source(url)
+            .reverse_search("https://example.com/pony.png", false)
+            .unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/iqdb_queries.json?"));
+        assert_eq!(query_pairs(&request)["url"], "https://example.com/pony.png");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, 5_021_877);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{config::BooruApi, CONFIG};
+use error_chain::error_chain;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+mod danbooru;
+mod philomena;
+#[cfg(test)]
+mod test_server;
+
+pub use danbooru::Danbooru;
+pub use philomena::Philomena;
+
+error_chain! {
+    foreign_links {
+        Http(::reqwest::Error);
+        Url(::url::ParseError);
+    }
+
+    errors {
+        UnknownSource(name: String) {
+            description("unknown image source")
+            display("unknown image source {}", name)
+        }
+        InvalidQuery(term: String) {
+            description("invalid search term")
+            display("invalid search term {}", term)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Image {
+    pub id: u32,
+    pub url: String,
+    pub image_url: String,
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub first_seen_at: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SearchResult {
+    pub images: Vec<Image>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Random,
+    Top,
+    New,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort::Random
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Query {
+    pub tags: Vec<String>,
+    pub excluded: Vec<String>,
+    pub min_score: Option<i64>,
+    pub sort: Sort,
+}
+
+impl Query {
+    // comma-separated terms: tags, -excluded tags, score.gt:/score.gte: and sort:top/new/random
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut query = Self::default();
+        for term in text.split(',').map(|term| term.trim().to_lowercase()) {
+            if term.is_empty() {
+                continue;
+            }
+            let invalid = || ErrorKind::InvalidQuery(term.clone());
+            if term.starts_with("score.gt:") {
+                query.min_score = Some(
+                    term[9..]
+                        .trim()
+                        .parse::<i64>()
+                        .ok()
+                        .and_then(|score| score.checked_add(1))
+                        .ok_or_else(invalid)?,
+                );
+            } else if term.starts_with("score.gte:") {
+                query.min_score = Some(term[10..].trim().parse().map_err(|_| invalid())?);
+            } else if term.starts_with("sort:") {
+                query.sort = match term[5..].trim() {
+                    "random" => Sort::Random,
+                    "top" => Sort::Top,
+                    "new" => Sort::New,
+                    _ => return Err(invalid().into()),
+                };
+            } else if term.starts_with('-') {
+                let tag = term[1..].trim();
+                if tag.is_empty() {
+                    return Err(invalid().into());
+                }
+                query.excluded.push(tag.to_owned());
+            } else {
+                query.tags.push(term);
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.excluded.is_empty() && self.min_score.is_none()
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms: Vec<String> = self.tags.clone();
+        terms.extend(self.excluded.iter().map(|tag| format!("-{}", tag)));
+        if let Some(score) = self.min_score {
+            terms.push(format!("score.gte:{}", score));
+        }
+        if terms.is_empty() {
+            terms.push("*".to_owned());
+        }
+        match self.sort {
+            Sort::Random => {}
+            Sort::Top => terms.push("sort:top".to_owned()),
+            Sort::New => terms.push("sort:new".to_owned()),
+        }
+        write!(f, "{}", terms.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub struct Tag {
+    pub name: String,
+    pub images: u64,
+    pub aliased_to: Option<String>,
+}
+
+fn resolve_alias<'a>(aliases: &'a HashMap<String, HashSet<String>>, tag: &'a str) -> &'a str {
+    aliases
+        .iter()
+        .find(|(_tag, aliases)| aliases.contains(tag))
+        .map_or(tag, |(tag, _aliases)| tag.as_ref())
+}
+
+pub trait ImageSource {
+    fn name(&self) -> &str;
+    fn search(&self, query: &Query, nsfw: bool) -> Result<SearchResult>;
+    fn tags(&self, prefix: &str) -> Result<Vec<Tag>>;
+    fn reverse_search(&self, image_url: &str, nsfw: bool) -> Result<Vec<Image>>;
+}
+
+pub fn get_source(name: Option<&str>) -> Result<Box<dyn ImageSource>> {
+    let name = name.unwrap_or(&CONFIG.gib.default_source).to_lowercase();
+    let config = CONFIG
+        .gib
+        .sources
+        .get(&name)
+        .ok_or_else(|| ErrorKind::UnknownSource(name.clone()))?;
+    Ok(match config.api {
+        BooruApi::Philomena => Box::new(Philomena::new(name, config)),
+        BooruApi::Danbooru => Box::new(Danbooru::new(name, config)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| (*tag).to_owned()).collect()
+    }
+
+    #[test]
+    fn query_parses_terms() {
+        let query =
+            Query::parse("Twilight Sparkle, -Grimdark,cute, score.gt:100, sort:top").unwrap();
+        assert_eq!(
+            query,
+            Query {
+                tags: tags(&["twilight sparkle", "cute"]),
+                excluded: tags(&["grimdark"]),
+                min_score: Some(101),
+                sort: Sort::Top,
+            }
+        );
+        assert_eq!(
+            query.to_string(),
+            "twilight sparkle, cute, -grimdark, score.gte:101, sort:top"
+        );
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let query = Query::parse(" , ").unwrap();
+        assert!(query.is_empty());
+        assert_eq!(query.sort, Sort::Random);
+        assert_eq!(query.to_string(), "*");
+        assert_eq!(Query::parse("sort:new").unwrap().to_string(), "*, sort:new");
+    }
+
+    #[test]
+    fn query_rejects_invalid_terms() {
+        for text in &[
+            "score.gt:lots",
+            "score.gt:9223372036854775807",
+            "sort:best",
+            "cute, -",
+            "score.gte:",
+        ] {
+            match Query::parse(text) {
+                Err(Error(ErrorKind::InvalidQuery(_), _)) => {}
+                other => panic!("{} parsed as {:?}", text, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use super::{resolve_alias, Image, ImageSource, Query, Result, SearchResult, Sort, Tag};
+use crate::{config::ImageSourceConfig, textile};
+use log::trace;
+use reqwest;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_default_from_null;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+const PER_PAGE: &str = "50";
+const REVERSE_DISTANCE: &str = "0.25";
+
+#[derive(Debug, Deserialize)]
+struct ImageResponse {
+    images: Vec<PhilomenaImage>,
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReverseResponse {
+    images: Vec<PhilomenaImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhilomenaImage {
+    id: u32,
+    #[serde(deserialize_with = "deserialize_default_from_null")]
+    tags: Vec<String>,
+    #[serde(deserialize_with = "deserialize_default_from_null")]
+    description: String,
+    #[serde(deserialize_with = "deserialize_default_from_null")]
+    name: String,
+    first_seen_at: Option<String>,
+    representations: RepresentationList,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepresentationList {
+    tall: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagResponse {
+    tags: Vec<PhilomenaTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhilomenaTag {
+    name: String,
+    images: u64,
+    aliased_tag: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Philomena {
+    name: String,
+    base_url: Url,
+    filter: Option<u32>,
+    nsfw_filter: Option<u32>,
+    aliases: HashMap<String, HashSet<String>>,
+}
+
+impl Philomena {
+    pub fn new(name: String, config: &ImageSourceConfig) -> Self {
+        Self {
+            name,
+            base_url: config.url.clone(),
+            filter: config.filter,
+            nsfw_filter: config.nsfw_filter,
+            aliases: config.aliases.clone(),
+        }
+    }
+
+    fn filter(&self, nsfw: bool) -> Option<u32> {
+        if nsfw {
+            self.nsfw_filter.or(self.filter)
+        } else {
+            self.filter
+        }
+    }
+
+    fn convert(&self, image: PhilomenaImage) -> Result<Image> {
+        Ok(Image {
+            url: self
+                .base_url
+                .join(&image.id.to_string())?
+                .as_str()
+                .to_owned(),
+            id: image.id,
+            image_url: image.representations.tall,
+            name: image.name,
+            description: textile::to_markdown(&image.description, &self.base_url),
+            tags: image.tags,
+            first_seen_at: image.first_seen_at,
+        })
+    }
+}
+
+impl ImageSource for Philomena {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn search(&self, query: &Query, nsfw: bool) -> Result<SearchResult> {
+        let mut terms: Vec<String> = query
+            .tags
+            .iter()
+            .map(|tag| resolve_alias(&self.aliases, tag).to_owned())
+            .chain(
+                query
+                    .excluded
+                    .iter()
+                    .map(|tag| format!("-{}", resolve_alias(&self.aliases, tag))),
+            )
+            .collect();
+        if let Some(score) = query.min_score {
+            terms.push(format!("score.gte:{}", score));
+        }
+        if terms.is_empty() {
+            terms.push("*".to_owned());
+        }
+        let search = terms
+            .iter()
+            .map(|term| term.replace(" ", "+"))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let sort = match query.sort {
+            Sort::Random => "random",
+            Sort::Top => "score",
+            Sort::New => "first_seen_at",
+        };
+        let mut params = vec![
+            ("sf", sort.to_owned()),
+            ("sd", "desc".to_owned()),
+            ("per_page", PER_PAGE.to_owned()),
+            ("q", search),
+        ];
+        if let Some(filter) = self.filter(nsfw) {
+            params.push(("filter_id", filter.to_string()));
+        }
+        let url = Url::parse_with_params(
+            self.base_url.join("api/v1/json/search/images")?.as_ref(),
+            &params,
+        )?;
+        trace!("Search URL: {}", url);
+
+        let response: ImageResponse =
+            reqwest::blocking::get(&url.as_ref().replace("%2B", "+"))?.json()?;
+        Ok(SearchResult {
+            total: response.total,
+            images: response
+                .images
+                .into_iter()
+                .map(|image| self.convert(image))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    fn tags(&self, prefix: &str) -> Result<Vec<Tag>> {
+        let prefix: String = prefix
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter(|c| !matches!(c, ',' | '*' | '?' | '"' | '(' | ')'))
+            .collect();
+        let url = Url::parse_with_params(
+            self.base_url.join("api/v1/json/search/tags")?.as_ref(),
+            &[
+                ("per_page", PER_PAGE.to_owned()),
+                ("q", format!("name:{}*", prefix)),
+            ],
+        )?;
+        trace!("Tag search URL: {}", url);
+
+        let response: TagResponse = reqwest::blocking::get(url)?.json()?;
+        let mut tags: Vec<_> = response
+            .tags
+            .into_iter()
+            .map(|tag| Tag {
+                name: tag.name,
+                images: tag.images,
+                aliased_to: tag.aliased_tag,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.images.cmp(&a.images).then_with(|| a.name.cmp(&b.name)));
+        Ok(tags)
+    }
+
+    fn reverse_search(&self, image_url: &str, nsfw: bool) -> Result<Vec<Image>> {
+        let mut params = vec![
+            ("url", image_url.to_owned()),
+            ("distance", REVERSE_DISTANCE.to_owned()),
+        ];
+        if let Some(filter) = self.filter(nsfw) {
+            params.push(("filter_id", filter.to_string()));
+        }
+        let url = Url::parse_with_params(
+            self.base_url.join("api/v1/json/search/reverse")?.as_ref(),
+            &params,
+        )?;
+        trace!("Reverse search URL: {}", url);
+
+        let response: ReverseResponse = reqwest::blocking::Client::new()
+            .post(url)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        response
+            .images
+            .into_iter()
+            .map(|image| self.convert(image))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{booru::test_server, config::BooruApi};
+    use maplit::{hashmap, hashset};
+
+    fn source(url: Url) -> Philomena {
+        Philomena::new(
+            "derpibooru".to_owned(),
+            &ImageSourceConfig {
+                api: BooruApi::Philomena,
+                url,
+                filter: Some(100),
+                nsfw_filter: Some(200),
+                aliases: hashmap! {
+                    "twilight sparkle".to_owned() => hashset! {"twi".to_owned(), "ts".to_owned()},
+                },
+            },
+        )
+    }
+
+    fn query_pairs(request: &str) -> HashMap<String, String> {
+        Url::parse("http://localhost")
+            .unwrap()
+            .join(request)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[test]
+    fn search_expands_aliases() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_images.json"));
+        source(url)
+            .search(&Query::parse("twi, cute").unwrap(), false)
+            .unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/api/v1/json/search/images?"));
+        let query = query_pairs(&request);
+        assert_eq!(query["q"], "twilight sparkle,cute");
+        assert_eq!(query["sf"], "random");
+        assert_eq!(query["filter_id"], "100");
+    }
+
+    #[test]
+    fn search_excludes_scores_and_sorts() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_empty.json"));
+        source(url)
+            .search(
+                &Query::parse("cute, -ts, score.gt:99, sort:new").unwrap(),
+                false,
+            )
+            .unwrap();
+
+        let query = query_pairs(&requests.recv().unwrap());
+        assert_eq!(query["q"], "cute,-twilight sparkle,score.gte:100");
+        assert_eq!(query["sf"], "first_seen_at");
+        assert_eq!(query["sd"], "desc");
+    }
+
+    #[test]
+    fn search_without_tags_matches_everything() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_empty.json"));
+        let result = source(url).search(&Query::default(), false).unwrap();
+
+        assert_eq!(query_pairs(&requests.recv().unwrap())["q"], "*");
+        assert_eq!(result.total, 0);
+        assert!(result.images.is_empty());
+    }
+
+    #[test]
+    fn search_uses_nsfw_filter() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_empty.json"));
+        source(url)
+            .search(&Query::parse("cute").unwrap(), true)
+            .unwrap();
+
+        assert_eq!(query_pairs(&requests.recv().unwrap())["filter_id"], "200");
+    }
+
+    #[test]
+    fn search_parses_images() {
+        let (url, _requests) = test_server::serve(include_str!("fixtures/search_images.json"));
+        let result = source(url.clone())
+            .search(&Query::parse("twi").unwrap(), false)
+            .unwrap();
+
+        assert_eq!(result.total, 12874);
+        assert_eq!(result.images.len(), 2);
+
+        let first = &result.images[0];
+        assert_eq!(first.id, 1_432_534);
+        assert_eq!(first.url, url.join("1432534").unwrap().as_str());
+        assert_eq!(
+            first.image_url,
+            "https://derpicdn.net/img/2017/5/18/1432534/tall.png"
+        );
+        assert_eq!(first.name, "twilight_reading.png");
+        assert_eq!(
+            first.description,
+            "A **quick** sketch for [my gallery](https://example.com/gallery)"
+        );
+        assert!(first.tags.contains(&"artist:somepony".to_owned()));
+
+        let second = &result.images[1];
+        assert!(second.name.is_empty());
+        assert!(second.description.is_empty());
+        assert!(second.tags.is_empty());
+        assert_eq!(second.first_seen_at, None);
+    }
+
+    #[test]
+    fn reverse_search_finds_images() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_images.json"));
+        let images = source(url)
+            .reverse_search("https://example.com/pony.png", true)
+            .unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/api/v1/json/search/reverse?"));
+        let query = query_pairs(&request);
+        assert_eq!(query["url"], "https://example.com/pony.png");
+        assert_eq!(query["filter_id"], "200");
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id, 1_432_534);
+    }
+
+    #[test]
+    fn tags_search_by_prefix() {
+        let (url, requests) = test_server::serve(include_str!("fixtures/search_tags.json"));
+        let tags = source(url).tags(" Twilight Sp*").unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/api/v1/json/search/tags?"));
+        assert_eq!(query_pairs(&request)["q"], "name:twilight sp*");
+
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "twilight sparkle",
+                "twilight sparkle (alicorn)",
+                "twilight sparks"
+            ]
+        );
+        assert_eq!(tags[0].images, 312_454);
+        assert_eq!(tags[0].aliased_to, None);
+        assert_eq!(tags[2].aliased_to, Some("twilight sparkle".to_owned()));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+use url::Url;
+
+// answers a single request with `body`, sending back the requested path and query
+pub fn serve(body: &'static str) -> (Url, Receiver<String>) {
+    serve_all(vec![body])
+}
+
+// answers one request per body, in order
+pub fn serve_all(bodies: Vec<&'static str>) -> (Url, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("unable to bind test server");
+    let url = Url::parse(&format!(
+        "http://{}/",
+        listener.local_addr().expect("test server has no address")
+    ))
+    .expect("invalid test server URL");
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for body in bodies {
+            let (mut stream, _addr) = listener.accept().expect("no test request");
+            let mut reader = BufReader::new(stream.try_clone().expect("unable to clone stream"));
+
+            let mut request_line = String::new();
+            reader
+                .read_line(&mut request_line)
+                .expect("no request line");
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+                    break;
+                }
+            }
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .expect("unable to write response");
+            sender
+                .send(
+                    request_line
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_owned(),
+                )
+                .ok();
+        }
+    });
+
+    (url, receiver)
+}
@@ -1,129 +1,194 @@
-use crate::{db, CONFIG};
+use crate::{
+    booru::{self, Image, ImageSource},
+    config::GibSeenScope,
+    db, util, CONFIG,
+};
 use digit_group::FormatGroup;
 use lazy_static::lazy_static;
-use log::trace;
 use rand::{self, seq::SliceRandom};
-use regex::Regex;
-use reqwest;
-use serde::Deserialize;
-use serde_aux::field_attributes::deserialize_default_from_null;
 use serenity::{
-    framework::standard::{macros::command, Args, CommandResult},
+    builder::CreateEmbed,
+    framework::standard::{
+        macros::{check, command},
+        Args, CheckResult, CommandError, CommandOptions, CommandResult,
+    },
     model::prelude::*,
     prelude::*,
     utils::{Colour, MessageBuilder},
 };
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
 use url::Url;
 
 const MAX_ARTISTS: usize = 4;
+const STATS_LENGTH: u32 = 15;
+const TAG_SUGGESTIONS: usize = 10;
+const MAX_GALLERY: usize = 4;
+const MAX_MATCHES: usize = 3;
+pub const GIB_DELAY: i64 = 10;
+
+type SourceArg<'a> = (Box<dyn ImageSource>, &'a str);
+
+lazy_static! {
+    static ref COOLDOWNS: Mutex<HashMap<UserId, Instant>> = Mutex::default();
+}
+
+fn format_artists(tags: &[String]) -> Option<String> {
+    let artists: Vec<_> = tags
+        .iter()
+        .filter_map(|tag| {
+            if tag.starts_with("artist:") {
+                Some(&tag[7..])
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if artists.is_empty() {
+        None
+    } else if artists.len() > MAX_ARTISTS {
+        Some(format!(
+            "{} & {} others",
+            artists[..MAX_ARTISTS - 1].join(" & "),
+            artists.len() - (MAX_ARTISTS - 1)
+        ))
+    } else {
+        Some(artists.join(" & "))
+    }
+}
+
+fn clip_description(description: String, max_length: usize) -> String {
+    if description.len() > max_length {
+        format!("{}\u{2026}", util::truncate(&description, max_length))
+    } else {
+        description
+    }
+}
 
-#[derive(Debug, Deserialize)]
-pub struct ImageResponse {
-    images: Vec<Image>,
-    total: usize,
+// splits an optional leading @source off the arguments, replying if there's no such booru
+pub(super) fn source_arg<'a>(
+    context: &Context,
+    message: &Message,
+    text: &'a str,
+) -> Result<Option<SourceArg<'a>>, CommandError> {
+    let text = text.trim();
+    let (source, text) = if text.starts_with('@') {
+        let (source, rest) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+        (Some(&source[1..]), rest.trim())
+    } else {
+        (None, text)
+    };
+    match booru::get_source(source) {
+        Ok(source) => Ok(Some((source, text))),
+        Err(booru::Error(booru::ErrorKind::UnknownSource(name), _)) => {
+            message.reply(
+                context,
+                &format!(
+                    "I don't know a booru called {}! <:lyou:350623520494977035>",
+                    name
+                ),
+            )?;
+            Ok(None)
+        }
+        Err(err) => Err(err.into()),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Image {
-    id: u32,
-    #[serde(deserialize_with = "deserialize_default_from_null")]
-    tags: Vec<String>,
-    #[serde(deserialize_with = "deserialize_default_from_null")]
-    description: String,
-    #[serde(deserialize_with = "deserialize_default_from_null")]
-    name: String,
-    first_seen_at: Option<String>,
-    representations: RepresentationList,
+// a leading number asks for a gallery of that many images
+fn split_count(text: &str) -> (usize, &str) {
+    let (word, rest) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+    match word.parse::<usize>() {
+        Ok(count) if !rest.trim().is_empty() => (count.clamp(1, MAX_GALLERY), rest.trim()),
+        _ => (1, text),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RepresentationList {
-    tall: String,
+pub fn ratelimited(secs: i64) -> String {
+    format!(
+        "Don't spam! Try again in {} second{}...",
+        secs,
+        if secs == 1 { "" } else { "s" }
+    )
+}
+
+fn start_cooldown(user: UserId, images: usize) {
+    if images > 1 {
+        let delay = Duration::from_secs(GIB_DELAY as u64 * (images as u64 - 1));
+        COOLDOWNS.lock().insert(user, Instant::now() + delay);
+    }
+}
+
+// the derp bucket only counts commands, so each image past the first adds its delay here
+#[check]
+#[name = "Derp"]
+fn derp_check(_: &mut Context, message: &Message, _: &mut Args, _: &CommandOptions) -> CheckResult {
+    let now = Instant::now();
+    let mut cooldowns = COOLDOWNS.lock();
+    cooldowns.retain(|_user, until| *until > now);
+    match cooldowns.get(&message.author.id) {
+        Some(until) => {
+            let wait = *until - now;
+            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            CheckResult::new_user(ratelimited(i64::try_from(secs).unwrap_or(i64::MAX)))
+        }
+        None => CheckResult::Success,
+    }
+}
+
+fn pick_unseen(images: &[Image], count: usize, is_seen: impl Fn(&Image) -> bool) -> Vec<&Image> {
+    let (unseen, seen): (Vec<_>, Vec<_>) = images.iter().partition(|image| !is_seen(image));
+    unseen.into_iter().chain(seen).take(count).collect()
 }
 
 #[command]
-#[description("Gib pics from Derpibooru")]
-#[usage("[tags\u{2026}]")]
+#[description(
+    "Gib pics from Derpibooru or another booru. To search for the tags stats, alias or tags, follow them with a comma."
+)]
+#[usage("[@source] [count] [tags\u{2026}, -excluded\u{2026}, score.gt:n, sort:top|new|random]")]
 #[bucket("derp")]
+#[checks(Derp)]
+#[sub_commands(gib_stats, gib_alias, gib_tags)]
 pub fn gib(context: &mut Context, message: &Message, args: Args) -> CommandResult {
-    lazy_static! {
-        static ref REGEXES: Vec<(Regex, &'static str)> = [
-            // bold
-            (
-                r"(?P<s1>^|\s)\*(?P<t>[\w ]+?)\*(?P<s2>\s|$)",
-                "$s1**$t**$s2"
-            ),
-            // italics
-            (r"(?P<s1>^|\s)_(?P<t>[\w ]+?)_(?P<s2>\s|$)", "$s1*$t*$s2"),
-            // underline
-            (
-                r"(?P<s1>^|\s)\+(?P<t>[\w ]+?)\+(?P<s2>\s|$)",
-                r"$s1__${t}__$s2"
-            ),
-            // inline code
-            (r"(?P<s1>^|\s)@(?P<t>[\w ]+?)@(?P<s2>\s|$)", "$s1`$t`$s2"),
-            // strikethrough
-            (
-                r"(?P<s1>^|\s)\-(?P<t>[\w ]+?)\-(?P<s2>\s|$)",
-                "$s1~~$t~~$s2"
-            ),
-            // superscript
-            (r"(?P<s1>^|\s)\^(?P<t>[\w ]+?)\^(?P<s2>\s|$)", "$s1$t$s2"),
-            // subscript
-            (r"(?P<s1>^|\s)\~(?P<t>[\w ]+?)\~(?P<s2>\s|$)", "$s1$t$s2"),
-            // block quote
-            (r"\[bq\]", ""),
-            (r"\[/bq\]", ""),
-            // spoiler
-            (r"\[spoiler\]", ""),
-            (r"\[/spoiler\]", ""),
-            // link
-            (r#""(?P<t>.+?)":(?P<u>\S+)"#, "[$t]($u)"),
-            // image embed
-            (r"(?P<s1>^|\s)!(?P<t>\S+?)!(?P<s2>\s)", "$s1[Image]($t)$s2"),
-            // no parse
-            (r"\[==(?P<t>[\w ]+?)==\]", "$t"),
-        ].iter()
-            .map(|x| (Regex::new(x.0).unwrap(), x.1))
-            .collect();
-    }
-
-    let search = args
-        .message()
-        .split(',')
-        .map(|arg| {
-            let arg = arg.trim();
-            CONFIG
-                .gib
-                .aliases
-                .iter()
-                .find(|(_tag, aliases)| aliases.contains(arg))
-                .map_or(arg, |(tag, _aliases)| tag.as_ref())
-                .replace(" ", "+")
-        })
-        .collect::<Vec<_>>()
-        .join(",");
-
-    let url = Url::parse_with_params(
-        "https://derpibooru.org/api/v1/json/search/images",
-        &[
-            ("sf", "random".to_owned()),
-            ("per_page", "50".to_owned()),
-            ("filter_id", CONFIG.gib.filter.to_string()),
-            (
-                "q",
-                if search.is_empty() {
-                    "*".to_owned()
-                } else {
-                    search
-                },
-            ),
-        ],
-    )?;
-    trace!("Search URL: {}", url);
+    let (source, text) = match source_arg(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let (count, text) = split_count(text);
 
-    let response: ImageResponse =
-        reqwest::blocking::get(&url.as_ref().replace("%2B", "+"))?.json()?;
+    let mut query = match booru::Query::parse(text) {
+        Ok(query) => query,
+        Err(booru::Error(booru::ErrorKind::InvalidQuery(term), _)) => {
+            message.reply(
+                &context,
+                &format!(
+                    "I don't understand {}! <:lyou:350623520494977035>",
+                    MessageBuilder::new().push_safe(term).build()
+                ),
+            )?;
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    db::with_db(|conn| {
+        for tag in query.tags.iter_mut().chain(query.excluded.iter_mut()) {
+            if let Some(alias) = db::get_gib_alias(&conn, source.name(), tag)? {
+                *tag = alias;
+            }
+        }
+        Ok(())
+    })?;
+    let nsfw = wants_nsfw(context, message)?;
+    let response = source.search(&query, nsfw)?;
+
+    let scope = match CONFIG.gib.seen_scope {
+        GibSeenScope::Channel => message.channel_id.to_string(),
+        GibSeenScope::User => message.author.id.to_string(),
+    };
+    let query = query.to_string();
 
     if response.images.is_empty() {
         message.reply(
@@ -134,77 +199,402 @@ pub fn gib(context: &mut Context, message: &Message, args: Args) -> CommandResul
                 .choose(&mut rand::thread_rng())
                 .map_or("", |reply| reply.as_ref()),
         )?;
-    } else if let Some(result) = db::with_db(|conn| {
-        let unseen = response
-            .images
-            .iter()
-            .find(|result| !db::gib_is_seen(&conn, result.id).unwrap_or(false))
-            .or_else(|| response.images.first());
-        if let Some(unseen) = unseen {
-            db::gib_seen(&conn, unseen.id)?;
+        return Ok(());
+    }
+
+    let results = db::with_db(|conn| {
+        let unseen = pick_unseen(&response.images, count, |image| {
+            db::gib_is_seen(&conn, source.name(), &scope, image.id).unwrap_or(false)
+        });
+        for image in &unseen {
+            db::gib_seen(&conn, source.name(), &scope, image.id)?;
+            db::gib_served(&conn, source.name(), &query)?;
         }
         Ok(unseen)
-    })? {
-        let url = Url::parse("https://derpibooru.org/")?.join(&result.id.to_string())?;
-        let artists: Vec<_> = result
-            .tags
+    })?;
+    start_cooldown(message.author.id, results.len());
+
+    let footer = format!(
+        "Out of {} results on {}",
+        response.total.format_si('.'),
+        source.name()
+    );
+    send_images(context, message.channel_id, &results, &footer)?;
+    Ok(())
+}
+
+#[command("source")]
+#[description("Find where a pony pic came from on Derpibooru or another booru")]
+#[usage("[@source] [image URL, or attach an image]")]
+#[bucket("derp")]
+#[checks(Derp)]
+pub fn reverse_search(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (source, text) = match source_arg(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let text = text.trim_start_matches('<').trim_end_matches('>');
+    let image_url = if text.is_empty() {
+        message
+            .attachments
             .iter()
-            .filter_map(|tag| {
-                if tag.starts_with("artist:") {
-                    Some(&tag[7..])
-                } else {
-                    None
-                }
-            })
-            .collect();
+            .find(|attachment| attachment.width.is_some())
+            .map(|attachment| attachment.url.clone())
+    } else {
+        Url::parse(text)
+            .ok()
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .map(|url| url.as_str().to_owned())
+    };
+    let image_url = if let Some(image_url) = image_url {
+        image_url
+    } else {
+        message.reply(&context, "Give me an image URL or attach an image!")?;
+        return Ok(());
+    };
 
-        let full_desc = REGEXES.iter().fold(
-            result.description.to_owned(),
-            |acc, (pattern, replacement)| pattern.replace_all(&acc, *replacement).into_owned(),
-        );
-        let description = if full_desc.len() > CONFIG.discord.long_msg_threshold {
-            format!(
-                "{}\u{2026}",
-                &full_desc[..CONFIG.discord.long_msg_threshold]
-            )
+    let nsfw = wants_nsfw(context, message)?;
+    let matches = source.reverse_search(&image_url, nsfw)?;
+    if matches.is_empty() {
+        message.reply(
+            &context,
+            &format!("I couldn't find that image on {}.", source.name()),
+        )?;
+        return Ok(());
+    }
+
+    let footer = format!(
+        "{} match{} on {}",
+        matches.len(),
+        if matches.len() == 1 { "" } else { "es" },
+        source.name()
+    );
+    let matches: Vec<&Image> = matches.iter().take(MAX_MATCHES).collect();
+    start_cooldown(message.author.id, matches.len());
+    send_images(context, message.channel_id, &matches, &footer)?;
+    Ok(())
+}
+
+fn wants_nsfw(context: &Context, message: &Message) -> Result<bool, CommandError> {
+    let blocks_nsfw = match (CONFIG.gib.block_nsfw_role, message.guild_id) {
+        (Some(role), Some(guild_id)) => message.author.has_role(context, guild_id, role)?,
+        _ => false,
+    };
+    Ok(!blocks_nsfw && db::with_db(|conn| db::is_channel_nsfw(&conn, message.channel_id))?)
+}
+
+// the v6 API only takes one embed per message
+fn send_images(
+    context: &Context,
+    channel: ChannelId,
+    images: &[&Image],
+    footer: &str,
+) -> serenity::Result<()> {
+    for image in images {
+        channel.send_message(context, |msg| msg.embed(|e| image_embed(e, image, footer)))?;
+    }
+    Ok(())
+}
+
+pub fn image_embed<'a>(e: &'a mut CreateEmbed, image: &Image, footer: &str) -> &'a mut CreateEmbed {
+    let mut e = e;
+    if let Some(ref timestamp) = image.first_seen_at {
+        e = e.timestamp(timestamp.to_owned());
+    }
+    if let Some(ref artists) = format_artists(&image.tags) {
+        e = e.author(|a| a.name(artists));
+    }
+    let description =
+        clip_description(image.description.clone(), CONFIG.discord.long_msg_threshold);
+    if !description.is_empty() {
+        e = e.description(description);
+    }
+    e.colour(Colour::GOLD)
+        .title(if image.name.is_empty() {
+            "<no filename>".to_owned()
         } else {
-            full_desc
-        };
+            MessageBuilder::new().push_safe(&image.name).build()
+        })
+        .url(&image.url)
+        .image(&image.image_url)
+        .footer(|f| f.text(footer))
+}
 
-        message.channel_id.send_message(&context, |msg| {
-            msg.embed(|mut e| {
-                if let Some(ref timestamp) = result.first_seen_at {
-                    e = e.timestamp(timestamp.to_owned());
-                }
-                if !artists.is_empty() {
-                    if artists.len() > MAX_ARTISTS {
-                        e = e.author(|a| {
-                            a.name(&format!(
-                                "{} & {} others",
-                                artists[..MAX_ARTISTS - 1].join(" & "),
-                                artists.len() - (MAX_ARTISTS - 1)
-                            ))
-                        });
-                    } else {
-                        e = e.author(|a| a.name(&artists.join(" & ")));
-                    }
-                }
-                if !description.is_empty() {
-                    e = e.description(description);
+#[command("stats")]
+#[description("Show which searches gib has served the most")]
+#[num_args(0)]
+pub fn gib_stats(context: &mut Context, message: &Message, _: Args) -> CommandResult {
+    let (total, queries) = db::with_db(|conn| {
+        Ok((
+            db::get_gib_total(&conn)?,
+            db::get_gib_queries(&conn, STATS_LENGTH)?,
+        ))
+    })?;
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::GOLD)
+                .title("Most popular gib searches")
+                .description(if queries.is_empty() {
+                    "Nothing has been gibbed yet.".to_owned()
+                } else {
+                    queries
+                        .iter()
+                        .map(|(source, query, count)| {
+                            format!(
+                                "**{}** \u{00d7} {} on {}",
+                                count,
+                                MessageBuilder::new().push_safe(query).build(),
+                                source
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .footer(|f| f.text(format!("{} images served in total", total)))
+        })
+    })?;
+    Ok(())
+}
+
+// anyone may list aliases, but changing them needs Manage Messages
+#[check]
+#[name = "GibAlias"]
+fn gib_alias_check(
+    context: &mut Context,
+    message: &Message,
+    args: &mut Args,
+    _: &CommandOptions,
+) -> CheckResult {
+    let mut words = args.message().split_whitespace();
+    let mut action = words.next();
+    if action.map_or(false, |word| word.starts_with('@')) {
+        action = words.next();
+    }
+    if action.map_or(false, |action| action.eq_ignore_ascii_case("list")) {
+        return CheckResult::Success;
+    }
+
+    let allowed = message.guild(&context).map_or(false, |guild| {
+        guild
+            .read()
+            .member_permissions(message.author.id)
+            .manage_messages()
+    });
+    if allowed {
+        CheckResult::Success
+    } else {
+        CheckResult::new_user("You're not allowed to change gib aliases!")
+    }
+}
+
+#[command("alias")]
+#[description("Manage tag aliases for gib, on top of the configured ones")]
+#[usage("[@source] add alias, tag | remove alias | list")]
+#[min_args(1)]
+#[checks(GibAlias)]
+pub fn gib_alias(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (source, text) = match source_arg(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let (action, rest) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+    let action = action.to_lowercase();
+    let rest = rest.trim().to_lowercase();
+
+    match action.as_ref() {
+        "add" => {
+            let mut parts = rest.splitn(2, ',').map(str::trim);
+            match (parts.next(), parts.next()) {
+                (Some(alias), Some(tag)) if !alias.is_empty() && !tag.is_empty() => {
+                    db::with_db(|conn| {
+                        db::gib_alias(&conn, source.name(), alias, tag, message.author.id)
+                    })?;
                 }
-                e.colour(Colour::GOLD)
-                    .title(if result.name.is_empty() {
-                        "<no filename>".to_owned()
-                    } else {
-                        MessageBuilder::new().push_safe(&result.name).build()
-                    })
-                    .url(url)
-                    .image(&result.representations.tall)
-                    .footer(|f| {
-                        f.text(&format!("Out of {} results", response.total.format_si('.')))
-                    })
-            })
-        })?;
+                _ => return Err("usage: gib alias add alias, tag".into()),
+            }
+        }
+        "remove" => {
+            if !db::with_db(|conn| db::delete_gib_alias(&conn, source.name(), &rest))? {
+                message.reply(
+                    &context,
+                    &format!(
+                        "There is no alias called {} on {}!",
+                        MessageBuilder::new().push_safe(&rest).build(),
+                        source.name()
+                    ),
+                )?;
+                return Ok(());
+            }
+        }
+        "list" => {
+            let aliases = db::with_db(|conn| db::get_gib_aliases(&conn, source.name()))?;
+            let configured: usize = CONFIG
+                .gib
+                .sources
+                .get(source.name())
+                .map_or(0, |config| config.aliases.values().map(HashSet::len).sum());
+            message.channel_id.send_message(&context, |msg| {
+                msg.embed(|e| {
+                    e.colour(Colour::GOLD)
+                        .title(format!("Gib aliases on {}", source.name()))
+                        .description(if aliases.is_empty() {
+                            "No aliases have been added yet.".to_owned()
+                        } else {
+                            MessageBuilder::new()
+                                .push_safe(
+                                    aliases
+                                        .iter()
+                                        .map(|(alias, tag)| format!("{} \u{2192} {}", alias, tag))
+                                        .collect::<Vec<_>>()
+                                        .join("\n"),
+                                )
+                                .build()
+                        })
+                        .footer(|f| f.text(format!("Plus {} aliases from the config", configured)))
+                })
+            })?;
+            return Ok(());
+        }
+        other => return Err(format!("unknown gib alias action {}", other).into()),
     }
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command("tags")]
+#[description("Suggest booru tags starting with some text")]
+#[usage("[@source] prefix")]
+#[min_args(1)]
+pub fn gib_tags(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (source, prefix) = match source_arg(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    if prefix.is_empty() {
+        return Err("empty tag prefix".into());
+    }
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for tag in source.tags(prefix)? {
+        let suggestion = match tag.aliased_to {
+            Some(target) => format!("{} \u{2192} **{}**", tag.name, target),
+            None => format!("**{}** ({})", tag.name, tag.images.format_si('.')),
+        };
+        if !suggestions.contains(&suggestion) {
+            suggestions.push(suggestion);
+        }
+        if suggestions.len() >= TAG_SUGGESTIONS {
+            break;
+        }
+    }
+
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::GOLD)
+                .title(format!(
+                    "Tags on {} starting with {}",
+                    source.name(),
+                    MessageBuilder::new().push_safe(prefix).build()
+                ))
+                .description(if suggestions.is_empty() {
+                    "No tags found, check the spelling.".to_owned()
+                } else {
+                    suggestions.join("\n")
+                })
+        })
+    })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(id: u32) -> Image {
+        Image {
+            id,
+            url: format!("https://derpibooru.org/{}", id),
+            image_url: format!("https://derpicdn.net/{}/tall.png", id),
+            name: String::new(),
+            description: String::new(),
+            tags: Vec::new(),
+            first_seen_at: None,
+        }
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| (*tag).to_owned()).collect()
+    }
+
+    #[test]
+    fn artists_are_listed() {
+        assert_eq!(format_artists(&tags(&["safe", "cute"])), None);
+        assert_eq!(
+            format_artists(&tags(&["artist:a", "safe", "artist:b"])),
+            Some("a & b".to_owned())
+        );
+        assert_eq!(
+            format_artists(&tags(&["artist:a", "artist:b", "artist:c", "artist:d"])),
+            Some("a & b & c & d".to_owned())
+        );
+    }
+
+    #[test]
+    fn artists_are_truncated() {
+        assert_eq!(
+            format_artists(&tags(&[
+                "artist:a", "artist:b", "artist:c", "artist:d", "artist:e", "artist:f"
+            ])),
+            Some("a & b & c & 3 others".to_owned())
+        );
+    }
+
+    #[test]
+    fn descriptions_are_clipped() {
+        assert_eq!(clip_description("short".to_owned(), 10), "short");
+        assert_eq!(clip_description("exactly10!".to_owned(), 10), "exactly10!");
+        assert_eq!(
+            clip_description("this is too long".to_owned(), 7),
+            "this is\u{2026}"
+        );
+        assert_eq!(
+            clip_description("\u{1f434}\u{1f434}".to_owned(), 5),
+            "\u{1f434}\u{2026}"
+        );
+    }
+
+    fn ids(images: &[&Image]) -> Vec<u32> {
+        images.iter().map(|image| image.id).collect()
+    }
+
+    #[test]
+    fn unseen_images_are_preferred() {
+        let images = vec![image(1), image(2), image(3)];
+        assert_eq!(ids(&pick_unseen(&images, 1, |image| image.id < 3)), vec![3]);
+        assert_eq!(ids(&pick_unseen(&images, 1, |_image| false)), vec![1]);
+        assert_eq!(
+            ids(&pick_unseen(&images, 2, |image| image.id == 2)),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn seen_images_are_repeated_when_exhausted() {
+        let images = vec![image(1), image(2), image(3)];
+        assert_eq!(ids(&pick_unseen(&images, 1, |_image| true)), vec![1]);
+        assert_eq!(
+            ids(&pick_unseen(&images, 3, |image| image.id == 1)),
+            vec![2, 3, 1]
+        );
+        assert!(pick_unseen(&[], 2, |_image| false).is_empty());
+    }
+
+    #[test]
+    fn gallery_count_is_parsed() {
+        assert_eq!(split_count("3 twi, cute"), (3, "twi, cute"));
+        assert_eq!(split_count("9 twi"), (MAX_GALLERY, "twi"));
+        assert_eq!(split_count("0 twi"), (1, "twi"));
+        assert_eq!(split_count("1000"), (1, "1000"));
+        assert_eq!(split_count("twi, cute"), (1, "twi, cute"));
+    }
+}
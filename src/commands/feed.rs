@@ -0,0 +1,107 @@
+use super::derp::source_arg;
+use crate::{booru, db, feeds};
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+    utils::{Colour, MessageBuilder},
+};
+
+#[command]
+#[description("Manage booru feeds that post new images to a channel")]
+#[usage("add name #channel [@source] query | remove name | list")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(MANAGE_CHANNELS)]
+pub fn feed(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    match args.single::<String>()?.to_lowercase().as_ref() {
+        "add" => {
+            let name = args.single::<String>()?.to_lowercase();
+            let channel = args.single::<ChannelId>()?;
+            let (source, text) = match source_arg(context, message, args.rest())? {
+                Some(found) => found,
+                None => return Ok(()),
+            };
+            let query = match booru::Query::parse(text) {
+                Ok(query) if !query.is_empty() => query,
+                Ok(_) => return Err("empty feed query".into()),
+                Err(booru::Error(booru::ErrorKind::InvalidQuery(term), _)) => {
+                    message.reply(
+                        &context,
+                        &format!(
+                            "I don't understand {}! <:lyou:350623520494977035>",
+                            MessageBuilder::new().push_safe(term).build()
+                        ),
+                    )?;
+                    return Ok(());
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            let added = db::with_db(|conn| {
+                if feeds::get_feeds(&conn)?
+                    .iter()
+                    .any(|feed| feed.name == name)
+                {
+                    return Ok(false);
+                }
+                db::add_feed(
+                    &conn,
+                    &name,
+                    Some(source.name()),
+                    &query.to_string(),
+                    channel,
+                    message.author.id,
+                )
+            })?;
+            if !added {
+                message.reply(
+                    &context,
+                    &format!("There already is a feed called {}!", name),
+                )?;
+                return Ok(());
+            }
+        }
+        "remove" => {
+            let name = args.single::<String>()?.to_lowercase();
+            if !db::with_db(|conn| db::delete_feed(&conn, &name))? {
+                message.reply(
+                    &context,
+                    &format!("There is no feed called {} that I can remove!", name),
+                )?;
+                return Ok(());
+            }
+        }
+        "list" => {
+            let feeds = db::with_db(|conn| feeds::get_feeds(&conn))?;
+            message.channel_id.send_message(&context, |msg| {
+                msg.embed(|e| {
+                    e.colour(Colour::GOLD)
+                        .title("Booru feeds")
+                        .description(if feeds.is_empty() {
+                            "There are no feeds.".to_owned()
+                        } else {
+                            feeds
+                                .iter()
+                                .map(|feed| {
+                                    format!(
+                                        "**{}**{} on {} to <#{}>: {}",
+                                        feed.name,
+                                        if feed.configured { " (config)" } else { "" },
+                                        feed.source.as_deref().unwrap_or("default booru"),
+                                        feed.channel,
+                                        MessageBuilder::new().push_safe(&feed.query).build()
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                })
+            })?;
+            return Ok(());
+        }
+        other => return Err(format!("unknown feed action {}", other).into()),
+    }
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
@@ -1,15 +1,23 @@
 use super::READ_TIMEOUT;
-use crate::CONFIG;
-use lazy_static::lazy_static;
-use meval;
+use crate::{db, dice, util, CONFIG};
 use rand::{self, Rng};
-use regex::{Captures, Regex};
 use serenity::{
-    framework::standard::{macros::command, Args, CommandResult},
+    framework::standard::{macros::command, Args, CommandError, CommandResult},
     model::prelude::*,
     prelude::*,
-    utils::Colour,
+    utils::{Colour, MessageBuilder},
 };
+use std::cmp::Reverse;
+
+const MAX_MACRO_NAME: usize = 32;
+const RESERVED_MACRO_NAMES: &[&str] = &["save", "forget", "macros", "stats"];
+const RECENT_ROLLS: u32 = 5;
+const MAX_DIE_STATS: usize = 6;
+const MAX_SPARKLINE_SIDES: usize = 20;
+const MACROS_PER_PAGE: usize = 15;
+const MAX_LISTED_EXPRESSION: usize = 80;
+// Discord's limit on embed descriptions
+const MAX_DESCRIPTION_LENGTH: usize = 2048;
 
 #[command]
 #[description("pong")]
@@ -20,51 +28,322 @@ pub fn ping(context: &mut Context, message: &Message, _: Args) -> CommandResult
 }
 
 #[command]
-#[description("Cast die")]
-#[usage("1d6 + 2d20")]
+#[description("Cast die, or a saved roll macro")]
+#[usage("4d6kh3 + 2, d20! adv, 6d10>=7, 2d6r<2[fire] + 4dF to hit, or macro [+ modifiers]")]
+#[sub_commands(roll_save, roll_forget, roll_macros, roll_stats)]
 pub fn roll(context: &mut Context, message: &Message, args: Args) -> CommandResult {
-    lazy_static! {
-        static ref DIE_RE: Regex = Regex::new(r"(\d+)?d(\d+)").expect("Invalid DIE_RE");
-    }
-
     let original = if args.is_empty() {
         "1d6"
     } else {
-        args.message()
+        args.message().trim()
     };
-    let rolled = DIE_RE.replace_all(original, |caps: &Captures<'_>| {
-        let rolls: usize = caps
-            .get(1)
-            .and_then(|m| m.as_str().parse().ok())
-            .unwrap_or(1);
-        let sides: usize = caps
-            .get(2)
-            .and_then(|m| m.as_str().parse().ok())
-            .unwrap_or(6);
-        if rolls < 1 {
-            String::new()
-        } else if sides < 1 {
-            "0".to_owned()
-        } else {
-            let results: Vec<String> = (0..rolls)
-                .map(|_| rand::thread_rng().gen_range(1, sides + 1).to_string())
-                .collect();
-            results.join(" + ")
+    let (word, rest) =
+        original.split_at(original.find(char::is_whitespace).unwrap_or(original.len()));
+    let name = word.to_lowercase();
+    let saved =
+        db::with_db(|conn| db::get_roll_macro(&conn, message.author.id, message.guild_id, &name))?;
+    let expression = saved.as_ref().map_or_else(
+        || original.to_owned(),
+        |expression| format!("{}{}", expression, rest),
+    );
+
+    let rolled = match dice::roll(&expression, |sides| {
+        rand::thread_rng().gen_range(1, sides + 1)
+    }) {
+        Ok(rolled) => rolled,
+        Err(dice::Error(dice::ErrorKind::Syntax(..), _)) => {
+            return Err(format!("invalid dice {}", expression).into())
         }
-    });
-    let result = meval::eval_str(&rolled)?;
-    let output = format!("{} \u{2192} {} \u{2192} **{}**", original, rolled, result);
-    if result.to_string() == rolled
-        || original == rolled
+        Err(err) => {
+            message.reply(&context, &format!("{}! <:lyou:350623520494977035>", err))?;
+            return Ok(());
+        }
+    };
+    db::with_db(|conn| {
+        db::record_roll(
+            &conn,
+            message.author.id,
+            message.guild_id,
+            &rolled.expression,
+            rolled.total,
+            &rolled.faces,
+        )
+    })?;
+
+    let result = dice::format_number(rolled.total);
+    let label = rolled
+        .label
+        .or_else(|| saved.map(|_| name))
+        .map_or_else(String::new, |label| format!("{}: ", label));
+    let output = format!(
+        "{}{} \u{2192} {} \u{2192} **{}**",
+        label, rolled.expression, rolled.details, result
+    );
+    if result == rolled.details
+        || rolled.expression == rolled.details
         || output.len() > CONFIG.discord.long_msg_threshold
     {
-        message.reply(&context, &format!("{} \u{2192} **{}**", original, result))?;
+        message.reply(
+            &context,
+            &format!("{}{} \u{2192} **{}**", label, rolled.expression, result),
+        )?;
     } else {
         message.reply(&context, &output)?;
     }
     Ok(())
 }
 
+// splits off an optional --guild flag, replying if the author can't manage guild macros
+fn macro_owner<'a>(
+    context: &Context,
+    message: &Message,
+    text: &'a str,
+) -> Result<Option<(u64, &'a str)>, CommandError> {
+    let text = text.trim();
+    let (flag, rest) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+    if flag != "--guild" {
+        return Ok(Some((message.author.id.0, text)));
+    }
+
+    let allowed = message.guild(&context).map_or(false, |guild| {
+        guild
+            .read()
+            .member_permissions(message.author.id)
+            .manage_messages()
+    });
+    match message.guild_id {
+        Some(guild_id) if allowed => Ok(Some((guild_id.0, rest.trim()))),
+        _ => {
+            message.reply(
+                context,
+                "You're not allowed to change this server's roll macros!",
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+#[command("save")]
+#[description("Save a roll macro for yourself, or for the whole server with --guild")]
+#[usage("[--guild] name expression")]
+#[min_args(2)]
+pub fn roll_save(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (owner, text) = match macro_owner(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let (name, expression) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+    let name = name.to_lowercase();
+    let expression = expression.trim();
+
+    let valid_name = name.len() <= MAX_MACRO_NAME
+        && name.starts_with(char::is_alphabetic)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        && !RESERVED_MACRO_NAMES.contains(&name.as_ref())
+        && dice::validate(&name).is_err();
+    if !valid_name {
+        message.reply(
+            &context,
+            &format!(
+                "{} can't be a macro name! Use a word that isn't dice.",
+                MessageBuilder::new().push_safe(&name).build()
+            ),
+        )?;
+        return Ok(());
+    }
+    if let Err(err) = dice::validate(expression) {
+        message.reply(&context, &format!("{}! <:lyou:350623520494977035>", err))?;
+        return Ok(());
+    }
+
+    db::with_db(|conn| db::set_roll_macro(&conn, owner, &name, expression, message.author.id))?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command("forget")]
+#[description("Delete a roll macro")]
+#[usage("[--guild] name")]
+#[min_args(1)]
+pub fn roll_forget(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (owner, name) = match macro_owner(context, message, args.message())? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let name = name.to_lowercase();
+    if db::with_db(|conn| db::delete_roll_macro(&conn, owner, &name))? {
+        message.react(&context, '\u{2705}')?;
+    } else {
+        message.reply(
+            &context,
+            &format!(
+                "There is no macro called {}!",
+                MessageBuilder::new().push_safe(&name).build()
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+#[command("macros")]
+#[description("List your roll macros and the server's")]
+#[usage("[page]")]
+#[max_args(1)]
+pub fn roll_macros(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let page = if args.is_empty() {
+        1
+    } else {
+        args.single::<usize>()?
+    };
+    let macros =
+        db::with_db(|conn| db::get_roll_macros(&conn, message.author.id, message.guild_id))?;
+    let pages = ((macros.len() + MACROS_PER_PAGE - 1) / MACROS_PER_PAGE).max(1);
+    let page = page.max(1).min(pages);
+    let lines: Vec<String> = macros
+        .iter()
+        .skip((page - 1) * MACROS_PER_PAGE)
+        .take(MACROS_PER_PAGE)
+        .map(|(name, expression, guild)| {
+            format!(
+                "{}{} \u{2192} {}",
+                name,
+                if *guild { " (server)" } else { "" },
+                util::clip(expression, MAX_LISTED_EXPRESSION)
+            )
+        })
+        .collect();
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::GOLD)
+                .title("Roll macros")
+                .description(if lines.is_empty() {
+                    "No macros have been saved yet.".to_owned()
+                } else {
+                    util::clip(
+                        &MessageBuilder::new().push_safe(lines.join("\n")).build(),
+                        MAX_DESCRIPTION_LENGTH,
+                    )
+                })
+                .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+        })
+    })?;
+    Ok(())
+}
+
+// counts[i] is how many times face i + 1 came up
+#[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)]
+fn average_face(counts: &[i64]) -> Option<f64> {
+    let total: i64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let sum: i64 = counts
+        .iter()
+        .enumerate()
+        .map(|(face, count)| (face as i64 + 1) * count)
+        .sum();
+    Some(sum as f64 / total as f64)
+}
+
+// groups (sides, face, amount) rows sorted by sides into (sides, dice cast, counts per face),
+// most cast first
+fn die_stats(faces: &[(u32, u32, i64)], limit: usize) -> Vec<(u32, i64, Vec<i64>)> {
+    let mut die_stats: Vec<(u32, i64, Vec<i64>)> = Vec::new();
+    for (sides, face, amount) in faces {
+        if die_stats.last().map_or(true, |(last, _, _)| last != sides) {
+            die_stats.push((*sides, 0, vec![0; *sides as usize]));
+        }
+        if let Some((_, total, counts)) = die_stats.last_mut() {
+            *total += amount;
+            if let Some(count) = (*face as usize)
+                .checked_sub(1)
+                .and_then(|index| counts.get_mut(index))
+            {
+                *count += amount;
+            }
+        }
+    }
+    die_stats.sort_by_key(|(_, total, _)| Reverse(*total));
+    die_stats.truncate(limit);
+    die_stats
+}
+
+#[command("stats")]
+#[description("Show someone's roll history and how their dice have been landing")]
+#[usage("[@user]")]
+#[max_args(1)]
+pub fn roll_stats(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let user = if args.is_empty() {
+        message.author.id
+    } else {
+        args.single::<UserId>()?
+    };
+    let (count, recent, faces) = db::with_db(|conn| {
+        Ok((
+            db::get_roll_count(&conn, user)?,
+            db::get_recent_rolls(&conn, user, RECENT_ROLLS)?,
+            db::get_roll_faces(&conn, user)?,
+        ))
+    })?;
+
+    let die_stats = die_stats(&faces, MAX_DIE_STATS);
+
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            let e = e
+                .colour(Colour::GOLD)
+                .title("Roll statistics")
+                .description(format!("<@{}> has rolled {} times.", user, count));
+            if !recent.is_empty() {
+                e.field(
+                    "Recent rolls",
+                    MessageBuilder::new()
+                        .push_safe(
+                            recent
+                                .iter()
+                                .map(|(expression, total)| {
+                                    format!(
+                                        "{} \u{2192} {}",
+                                        expression,
+                                        dice::format_number(*total)
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        )
+                        .build(),
+                    false,
+                );
+            }
+            for (sides, total, counts) in &die_stats {
+                let distribution = if *sides as usize <= MAX_SPARKLINE_SIDES {
+                    format!("\n1 {} {}", util::sparkline(counts), sides)
+                } else {
+                    String::new()
+                };
+                e.field(
+                    format!("d{}", sides),
+                    format!(
+                        "{} cast, average {} (expected {:.2}){}",
+                        total,
+                        average_face(counts).map_or_else(
+                            || "unknown".to_owned(),
+                            |average| format!("{:.2}", average)
+                        ),
+                        f64::from(*sides + 1) / 2.0,
+                        distribution
+                    ),
+                    true,
+                );
+            }
+            e
+        })
+    })?;
+    Ok(())
+}
+
 #[command]
 #[description("Show information about the bot")]
 #[num_args(0)]
@@ -91,3 +370,48 @@ pub fn info(context: &mut Context, message: &Message, _: Args) -> CommandResult
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages() {
+        assert_eq!(average_face(&[1, 1, 1, 1, 1, 1]), Some(3.5));
+        assert_eq!(average_face(&[0, 0, 2]), Some(3.0));
+        assert_eq!(average_face(&[0, 0, 0, 0]), None);
+        assert_eq!(average_face(&[]), None);
+    }
+
+    #[test]
+    fn grouping() {
+        let faces = [(6, 1, 2), (6, 6, 3), (20, 20, 1), (20, 1, 1), (20, 7, 10)];
+        let stats = die_stats(&faces, 10);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].0, 20);
+        assert_eq!(stats[0].1, 12);
+        assert_eq!(stats[0].2[0], 1);
+        assert_eq!(stats[0].2[6], 10);
+        assert_eq!(stats[0].2[19], 1);
+        assert_eq!(stats[1], (6, 5, vec![2, 0, 0, 0, 0, 3]));
+    }
+
+    #[test]
+    fn grouping_limit() {
+        let faces = [(4, 1, 1), (6, 1, 3), (8, 1, 2)];
+        let sides: Vec<u32> = die_stats(&faces, 2)
+            .into_iter()
+            .map(|(sides, _, _)| sides)
+            .collect();
+        assert_eq!(sides, vec![6, 8]);
+    }
+
+    #[test]
+    fn impossible_faces_are_counted_but_not_placed() {
+        assert_eq!(
+            die_stats(&[(2, 0, 1), (2, 3, 1)], 1),
+            vec![(2, 2, vec![0, 0])]
+        );
+    }
+}
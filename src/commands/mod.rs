@@ -13,27 +13,41 @@ use std::{collections::HashSet, time::Duration};
 const READ_TIMEOUT: Duration = Duration::from_secs(2);
 
 mod derp;
+mod feed;
 mod misc;
+mod moderation;
+mod modmail;
 mod pin;
 mod ranks;
 
 use derp::*;
+use feed::*;
 use misc::*;
+use moderation::*;
+use modmail::*;
 use pin::*;
 use ranks::*;
 
+pub use derp::{image_embed, ratelimited, GIB_DELAY};
+pub use pin::spawn_pin_scheduler;
+pub use ranks::handle_rank_reaction;
+
 #[group]
-#[commands(gib)]
+#[commands(gib, reverse_search, feed)]
 struct Horse;
 
 #[group]
-#[commands(ranks, rank, join, leave, pin)]
+#[commands(ranks, rank, join, leave, rankmenu, pin, unpin)]
 struct Discord;
 
 #[group]
 #[commands(roll, ping, info)]
 struct Misc;
 
+#[group]
+#[commands(kick, ban, softban, unban, purge, modmail)]
+struct Moderation;
+
 #[help]
 #[lacking_conditions("hide")]
 #[lacking_ownership("hide")]
@@ -53,7 +67,8 @@ fn help_command(
 
 pub fn is_allowed(message: &Message, cmd: &str) -> bool {
     match cmd {
-        "pin" => CONFIG.discord.pin_channels.contains(&message.channel_id),
+        "pin" | "unpin" => CONFIG.discord.pin_channels.contains(&message.channel_id),
+        "modmail" => CONFIG.modmail.channel == message.channel_id,
         _ => can_respond_to(&message),
     }
 }
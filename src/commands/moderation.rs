@@ -0,0 +1,362 @@
+use super::READ_TIMEOUT;
+use crate::{db, discord_eventhandler::get_log_channels};
+use chrono::{Duration, Utc};
+use log::warn;
+use serenity::{
+    framework::standard::{macros::command, Args, CommandError, CommandResult},
+    model::prelude::*,
+    prelude::*,
+    utils::Colour,
+};
+use std::{collections::HashMap, convert::TryFrom};
+
+const MAX_PURGE: u64 = 100;
+const MAX_BAN_DELETE_DAYS: u8 = 7;
+const SOFTBAN_DELETE_DAYS: u8 = 1;
+
+#[derive(Debug, Clone, Copy)]
+enum ModerationAction {
+    Kick,
+    Ban,
+    Softban,
+    Unban,
+    Purge,
+}
+
+impl ModerationAction {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Kick => "kick",
+            Self::Ban => "ban",
+            Self::Softban => "softban",
+            Self::Unban => "unban",
+            Self::Purge => "purge",
+        }
+    }
+
+    #[inline]
+    fn colour(self) -> Colour {
+        match self {
+            Self::Kick | Self::Softban => Colour::ORANGE,
+            Self::Ban => Colour::DARK_RED,
+            Self::Unban => Colour::DARK_GREEN,
+            Self::Purge => Colour::BLUE,
+        }
+    }
+}
+
+fn reason(args: &Args) -> Option<&str> {
+    let rest = args.rest().trim();
+    if rest.is_empty() {
+        None
+    } else {
+        Some(rest)
+    }
+}
+
+fn record(
+    context: &Context,
+    message: &Message,
+    action: ModerationAction,
+    target: Option<UserId>,
+    summary: &str,
+    reason: Option<&str>,
+) -> CommandResult {
+    let guild_id = message.guild_id.ok_or(SerenityError::Other(
+        "Moderation is only available on a server",
+    ))?;
+
+    db::with_db(|conn| {
+        db::moderation_action(
+            &conn,
+            guild_id,
+            message.author.id,
+            target,
+            action.name(),
+            reason,
+        )
+    })?;
+
+    for log_channel in get_log_channels(context, guild_id) {
+        if let Err(err) = log_channel.send_message(context, |msg| {
+            msg.embed(|e| {
+                e.colour(action.colour())
+                    .description(format!(
+                        "**{}**\n{}",
+                        summary,
+                        reason.map_or_else(
+                            || "No reason given".to_owned(),
+                            |reason| format!("Reason: {}", reason)
+                        )
+                    ))
+                    .author(|a| {
+                        a.name(&message.author.tag())
+                            .icon_url(&message.author.face())
+                    })
+                    .timestamp(&message.timestamp)
+            })
+        }) {
+            warn!("Unable to add moderation action to log channel: {:?}", err);
+        }
+    }
+    Ok(())
+}
+
+fn highest_role(
+    context: &Context,
+    guild_id: GuildId,
+    positions: &HashMap<RoleId, i64>,
+    user_id: UserId,
+) -> Option<i64> {
+    guild_id.member(context, user_id).ok().map(|member| {
+        member
+            .roles
+            .iter()
+            .filter_map(|role| positions.get(role).copied())
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+// moderators may only act on members ranked below them, and never on the owner
+fn refuse_target(
+    context: &Context,
+    message: &Message,
+    guild_id: GuildId,
+    target: UserId,
+) -> Result<bool, CommandError> {
+    let (owner_id, positions) = {
+        let guild = guild_id
+            .to_guild_cached(context)
+            .ok_or(SerenityError::Other("Guild not cached"))?;
+        let guild = guild
+            .try_read_for(READ_TIMEOUT)
+            .ok_or(SerenityError::Other("Can't lock guild"))?;
+        let positions: HashMap<RoleId, i64> = guild
+            .roles
+            .iter()
+            .map(|(id, role)| (*id, role.position))
+            .collect();
+        (guild.owner_id, positions)
+    };
+
+    let refusal = if target == owner_id {
+        Some("I won't do that to the server owner!")
+    } else if message.author.id == owner_id {
+        None
+    } else {
+        match highest_role(context, guild_id, &positions, target) {
+            Some(target_position)
+                if highest_role(context, guild_id, &positions, message.author.id)
+                    .map_or(true, |position| position <= target_position) =>
+            {
+                Some("You can't do that to someone who ranks as high as you!")
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(refusal) = refusal {
+        message.reply(context, refusal)?;
+    }
+    Ok(refusal.is_some())
+}
+
+#[command]
+#[description("Kick a member from the server")]
+#[usage("@user [reason\u{2026}]")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(KICK_MEMBERS)]
+pub fn kick(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let guild_id = message.guild_id.ok_or(SerenityError::Other(
+        "Kicking is only available on a server",
+    ))?;
+    let user_id = args.single::<UserId>()?;
+    let reason = reason(&args);
+
+    if refuse_target(context, message, guild_id, user_id)? {
+        return Ok(());
+    }
+
+    if let Some(reason) = reason {
+        guild_id.kick_with_reason(&context, user_id, reason)?;
+    } else {
+        guild_id.kick(&context, user_id)?;
+    }
+
+    record(
+        context,
+        message,
+        ModerationAction::Kick,
+        Some(user_id),
+        &format!("<@{}> kicked <@{}>", message.author.id, user_id),
+        reason,
+    )?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command]
+#[description("Ban a user from the server, deleting up to a week of their messages")]
+#[usage("@user [days_to_delete] [reason\u{2026}]")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(BAN_MEMBERS)]
+pub fn ban(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let guild_id = message.guild_id.ok_or(SerenityError::Other(
+        "Banning is only available on a server",
+    ))?;
+    let user_id = args.single::<UserId>()?;
+    let days = if let Some(days) = args.current().and_then(|arg| arg.parse::<u8>().ok()) {
+        args.advance();
+        days.min(MAX_BAN_DELETE_DAYS)
+    } else {
+        0
+    };
+    let reason = reason(&args);
+
+    if refuse_target(context, message, guild_id, user_id)? {
+        return Ok(());
+    }
+
+    guild_id.ban(&context, user_id, &(days, reason.unwrap_or_default()))?;
+
+    record(
+        context,
+        message,
+        ModerationAction::Ban,
+        Some(user_id),
+        &format!(
+            "<@{}> banned <@{}> and deleted {} day{} of their messages",
+            message.author.id,
+            user_id,
+            days,
+            if days == 1 { "" } else { "s" }
+        ),
+        reason,
+    )?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command]
+#[description("Kick a member from the server and delete their recent messages")]
+#[usage("@user [reason\u{2026}]")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(BAN_MEMBERS)]
+pub fn softban(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let guild_id = message.guild_id.ok_or(SerenityError::Other(
+        "Softbanning is only available on a server",
+    ))?;
+    let user_id = args.single::<UserId>()?;
+    let reason = reason(&args);
+
+    if refuse_target(context, message, guild_id, user_id)? {
+        return Ok(());
+    }
+
+    guild_id.ban(
+        &context,
+        user_id,
+        &(SOFTBAN_DELETE_DAYS, reason.unwrap_or_default()),
+    )?;
+    guild_id.unban(&context, user_id)?;
+
+    record(
+        context,
+        message,
+        ModerationAction::Softban,
+        Some(user_id),
+        &format!("<@{}> softbanned <@{}>", message.author.id, user_id),
+        reason,
+    )?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command]
+#[description("Lift a ban")]
+#[usage("user_id [reason\u{2026}]")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(BAN_MEMBERS)]
+pub fn unban(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let guild_id = message.guild_id.ok_or(SerenityError::Other(
+        "Unbanning is only available on a server",
+    ))?;
+    let user_id = args.single::<UserId>()?;
+    let reason = reason(&args);
+
+    guild_id.unban(&context, user_id)?;
+
+    record(
+        context,
+        message,
+        ModerationAction::Unban,
+        Some(user_id),
+        &format!("<@{}> unbanned <@{}>", message.author.id, user_id),
+        reason,
+    )?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[command]
+#[description("Delete recent messages in the current channel, optionally only those by one user")]
+#[usage("count [@user] [reason\u{2026}]")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(MANAGE_MESSAGES)]
+pub fn purge(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let count = args.single::<u64>()?;
+    if !(1..=MAX_PURGE).contains(&count) {
+        message.reply(
+            &context,
+            &format!("I can only purge 1 to {} messages at once!", MAX_PURGE),
+        )?;
+        return Ok(());
+    }
+    let user_id = if let Some(id) = args.current().and_then(|arg| arg.parse::<UserId>().ok()) {
+        args.advance();
+        Some(id)
+    } else {
+        None
+    };
+    let reason = reason(&args);
+
+    // bulk deletion only works for messages younger than two weeks
+    let cutoff = Utc::now() - Duration::days(14);
+    let ids: Vec<MessageId> = message
+        .channel_id
+        .messages(&context, |req| req.before(message.id).limit(MAX_PURGE))?
+        .into_iter()
+        .filter(|msg| msg.timestamp > cutoff && user_id.map_or(true, |id| msg.author.id == id))
+        .take(usize::try_from(count)?)
+        .map(|msg| msg.id)
+        .collect();
+
+    match ids.len() {
+        0 => {}
+        1 => message.channel_id.delete_message(&context, ids[0])?,
+        _ => message.channel_id.delete_messages(&context, &ids)?,
+    }
+    message.delete(&context)?;
+
+    record(
+        context,
+        message,
+        ModerationAction::Purge,
+        user_id,
+        &format!(
+            "<@{}> purged {} message{}{} in <#{}>",
+            message.author.id,
+            ids.len(),
+            if ids.len() == 1 { "" } else { "s" },
+            user_id.map_or_else(String::new, |id| format!(" by <@{}>", id)),
+            message.channel_id
+        ),
+        reason,
+    )
+}
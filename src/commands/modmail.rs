@@ -0,0 +1,59 @@
+use crate::{db, modmail};
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+    utils::Colour,
+};
+
+#[command]
+#[description("Manage modmail tickets opened by DMing the bot")]
+#[usage("list | reply ticket text\u{2026} | close ticket")]
+#[min_args(1)]
+#[only_in("guilds")]
+#[required_permissions(MANAGE_MESSAGES)]
+#[help_available(false)]
+pub fn modmail(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let result = match args.single::<String>()?.to_lowercase().as_ref() {
+        "list" => {
+            let tickets = db::with_db(|conn| db::get_open_tickets(&conn))?;
+            message.channel_id.send_message(&context, |msg| {
+                msg.embed(|e| {
+                    e.colour(Colour::BLUE)
+                        .title("Open modmail tickets")
+                        .description(if tickets.is_empty() {
+                            "There are no open tickets.".to_owned()
+                        } else {
+                            tickets
+                                .iter()
+                                .map(|(ticket, user_id, opened)| {
+                                    format!("**#{}** from <@{}> since {}", ticket, user_id, opened)
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                })
+            })?;
+            Ok(())
+        }
+        "reply" => {
+            let ticket = args.single::<i64>()?;
+            let text = args.rest().trim();
+            if text.is_empty() {
+                return Err("empty modmail reply".into());
+            }
+            modmail::reply(&context, ticket, message.author.id, text)
+        }
+        "close" => modmail::close(&context, args.single::<i64>()?),
+        other => return Err(format!("unknown modmail action {}", other).into()),
+    };
+
+    match result {
+        Ok(()) => message.react(&context, '\u{2705}')?,
+        Err(modmail::Error(modmail::ErrorKind::NoSuchTicket(ticket), _)) => {
+            message.reply(&context, &format!("There is no open ticket #{}!", ticket))?;
+        }
+        Err(err) => return Err(err.into()),
+    }
+    Ok(())
+}
@@ -1,57 +1,835 @@
 use super::READ_TIMEOUT;
-use crate::discord_eventhandler::get_log_channels;
+use crate::{db, discord_eventhandler::get_log_channels, CONFIG};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use log::{error, trace, warn};
 use serenity::{
-    framework::standard::{macros::command, Args, CommandResult},
+    builder::CreateEmbed,
+    framework::standard::{
+        macros::{check, command},
+        Args, CheckResult, CommandError, CommandOptions, CommandResult,
+    },
+    http::{HttpError, StatusCode},
     model::prelude::*,
     prelude::*,
     utils::Colour,
 };
+use std::{sync::Once, thread, time::Duration};
+
+// unnamed pins, including those pinned before pins had names
+const DEFAULT_PIN: &str = "pin";
+const RESERVED_NAMES: &[&str] = &["list", "history", "revert"];
+const HISTORY_LENGTH: u32 = 10;
+const PREVIEW_LENGTH: usize = 80;
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+static SCHEDULER: Once = Once::new();
+
+#[derive(Debug, Default)]
+struct PinEmbed {
+    title: Option<String>,
+    colour: Option<Colour>,
+    description: Vec<String>,
+    fields: Vec<(String, Vec<String>)>,
+}
+
+impl PinEmbed {
+    // "# title", "colour: #rrggbb" and "## field name" lines, everything else is text
+    fn parse(text: &str) -> Option<Self> {
+        let mut embed = Self::default();
+        let mut markup = false;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if let Some(name) = trimmed.strip_prefix("## ") {
+                embed.fields.push((name.trim().to_owned(), Vec::new()));
+                markup = true;
+            } else if let Some(title) = trimmed.strip_prefix("# ") {
+                embed.title = Some(title.trim().to_owned());
+                markup = true;
+            } else if let Some(colour) = trimmed
+                .strip_prefix("colour:")
+                .or_else(|| trimmed.strip_prefix("color:"))
+                .and_then(|colour| {
+                    u32::from_str_radix(colour.trim().trim_start_matches('#'), 16).ok()
+                })
+            {
+                embed.colour = Some(Colour::new(colour));
+                markup = true;
+            } else if let Some((_name, value)) = embed.fields.last_mut() {
+                value.push(line.to_owned());
+            } else {
+                embed.description.push(line.to_owned());
+            }
+        }
+
+        if markup {
+            Some(embed)
+        } else {
+            None
+        }
+    }
+
+    fn apply<'a>(&self, e: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+        e.colour(self.colour.unwrap_or(Colour::BLUE));
+        if let Some(title) = &self.title {
+            e.title(title);
+        }
+        let description = self.description.join("\n");
+        if !description.trim().is_empty() {
+            e.description(description.trim());
+        }
+        for (name, value) in &self.fields {
+            let value = value.join("\n");
+            e.field(
+                name,
+                if value.trim().is_empty() {
+                    "\u{200b}"
+                } else {
+                    value.trim()
+                },
+                false,
+            );
+        }
+        e
+    }
+}
+
+// the bot's pin from before pins had names isn't in the database yet
+fn find_unnamed_pin(
+    context: &Context,
+    channel: &GuildChannel,
+) -> Result<Option<Message>, CommandError> {
+    let uid = context
+        .cache
+        .try_read_for(READ_TIMEOUT)
+        .map(|cache| cache.user.id)
+        .ok_or(SerenityError::Other("Can't lock cache"))?;
+    let known: Vec<MessageId> = db::with_db(|conn| db::get_pins(&conn, channel.id))?
+        .into_iter()
+        .map(|(_name, message_id)| message_id)
+        .collect();
+    Ok(channel
+        .pins(context)?
+        .into_iter()
+        .find(|msg| msg.author.id == uid && !known.contains(&msg.id)))
+}
+
+fn set_pin(context: &Context, channel: &GuildChannel, name: &str, pintext: &str) -> CommandResult {
+    let mut pinned = db::with_db(|conn| db::get_pin_message(&conn, channel.id, name))?
+        .and_then(|message_id| channel.message(context, message_id).ok());
+    if pinned.is_none() && name == DEFAULT_PIN {
+        pinned = find_unnamed_pin(context, channel)?;
+    }
+
+    let embed = PinEmbed::parse(pintext);
+    match pinned {
+        // an embed can't be removed from a message by editing it
+        Some(mut pinned) if pinned.embeds.is_empty() == embed.is_none() => {
+            pinned.edit(context, |edit| {
+                if let Some(embed) = &embed {
+                    edit.content("").embed(|e| embed.apply(e))
+                } else {
+                    edit.content(pintext)
+                }
+            })?;
+            db::with_db(|conn| db::set_pin_message(&conn, channel.id, name, pinned.id))?;
+        }
+        pinned => {
+            if let Some(pinned) = pinned {
+                if let Err(err) = pinned.delete(context) {
+                    warn!(
+                        "Unable to delete old pin {} in {}: {:?}",
+                        name, channel.id, err
+                    );
+                }
+            }
+            let pinned = if let Some(embed) = &embed {
+                channel.send_message(context, |msg| msg.embed(|e| embed.apply(e)))?
+            } else {
+                channel.send_message(context, |msg| msg.content(pintext))?
+            };
+            pinned.pin(context)?;
+            db::with_db(|conn| db::set_pin_message(&conn, channel.id, name, pinned.id))?;
+        }
+    }
+    Ok(())
+}
+
+fn log_pin(
+    context: &Context,
+    guild_id: GuildId,
+    author: Option<&User>,
+    summary: &str,
+    pintext: &str,
+) -> CommandResult {
+    for log_channel in get_log_channels(context, guild_id) {
+        log_channel.send_message(context, |msg| {
+            msg.embed(|e| {
+                e.colour(Colour::BLUE)
+                    .description(format!("**{}**\n{}", summary, pintext))
+                    .timestamp(&Utc::now());
+                if let Some(author) = author {
+                    e.author(|a| a.name(&author.tag()).icon_url(&author.face()));
+                }
+                e
+            })
+        })?;
+    }
+    Ok(())
+}
+
+fn preview(text: &str) -> String {
+    let text = text.replace('\n', " ");
+    if text.chars().count() > PREVIEW_LENGTH {
+        format!(
+            "{}\u{2026}",
+            text.chars().take(PREVIEW_LENGTH).collect::<String>()
+        )
+    } else {
+        text
+    }
+}
+
+fn list(context: &Context, message: &Message, channel: &GuildChannel) -> CommandResult {
+    let pins = db::with_db(|conn| db::get_pins(&conn, channel.id))?;
+    message.channel_id.send_message(context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::BLUE)
+                .title(format!("Pins in #{}", channel.name))
+                .description(if pins.is_empty() {
+                    "There are no pins in this channel.".to_owned()
+                } else {
+                    pins.iter()
+                        .map(|(name, message_id)| {
+                            format!(
+                                "**{}**: https://discordapp.com/channels/{}/{}/{}",
+                                name, channel.guild_id, channel.id, message_id
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+        })
+    })?;
+    Ok(())
+}
+
+fn history(
+    context: &Context,
+    message: &Message,
+    channel: &GuildChannel,
+    name: &str,
+) -> CommandResult {
+    let revisions =
+        db::with_db(|conn| db::get_pin_revisions(&conn, channel.id, name, HISTORY_LENGTH))?;
+    message.channel_id.send_message(context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::BLUE)
+                .title(format!("History of pin {} in #{}", name, channel.name))
+                .description(if revisions.is_empty() {
+                    "There are no recorded pin revisions.".to_owned()
+                } else {
+                    revisions
+                        .iter()
+                        .enumerate()
+                        .map(|(index, (author_id, content, time))| {
+                            format!(
+                                "**{}.** <@{}> at {}{}\n{}",
+                                index,
+                                author_id,
+                                time,
+                                if index == 0 { " (current)" } else { "" },
+                                preview(content)
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .footer(|f| {
+                    f.text(format!(
+                        "Use {}pin revert {} [n] to restore an earlier revision",
+                        CONFIG.discord.command_prefix, name
+                    ))
+                })
+        })
+    })?;
+    Ok(())
+}
+
+fn revert(
+    context: &Context,
+    message: &Message,
+    channel: &GuildChannel,
+    name: &str,
+    back: u32,
+) -> CommandResult {
+    let pintext = if let Some(pintext) =
+        db::with_db(|conn| db::get_pin_revision(&conn, channel.id, name, back))?
+    {
+        pintext
+    } else {
+        message.reply(
+            context,
+            &format!(
+                "Pin {} has no revision {}! <:lyou:350623520494977035>",
+                name, back
+            ),
+        )?;
+        return Ok(());
+    };
+
+    set_pin(context, channel, name, &pintext)?;
+    db::with_db(|conn| db::pin_revision(&conn, channel.id, name, message.author.id, &pintext))?;
+    log_pin(
+        context,
+        channel.guild_id,
+        Some(&message.author),
+        &format!(
+            "<@{}> reverted pin {} on <#{}> to revision {}",
+            message.author.id, name, channel.id, back
+        ),
+        &pintext,
+    )
+}
+
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M")
+                .map(|time| Utc.from_utc_datetime(&time))
+        })
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+                .map(|time| Utc.from_utc_datetime(&time))
+        })
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let (word, rest) = text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()));
+    (word, rest.trim_start())
+}
+
+// "pin rules text" only names the pin if rules is a known name, otherwise it's all pin text
+fn split_name<'a>(text: &'a str, known: &[String]) -> (Option<String>, &'a str) {
+    let (word, rest) = split_word(text);
+    let word = word.to_lowercase();
+    if !rest.is_empty() && known.contains(&word) {
+        (Some(word), rest)
+    } else {
+        (None, text)
+    }
+}
+
+fn publish(context: &Context, pin: &db::ScheduledPin) -> CommandResult {
+    let channel = pin
+        .channel_id
+        .to_channel(context)?
+        .guild()
+        .ok_or(SerenityError::Other("Scheduled pin outside of a guild"))?;
+    let channel = channel
+        .try_read_for(READ_TIMEOUT)
+        .ok_or(SerenityError::Other("Can't lock channel"))?;
+    set_pin(context, &channel, &pin.name, &pin.content)?;
+    db::with_db(|conn| {
+        db::pin_revision(&conn, channel.id, &pin.name, pin.author_id, &pin.content)
+    })?;
+    if let Err(err) = log_pin(
+        context,
+        channel.guild_id,
+        pin.author_id.to_user(context).ok().as_ref(),
+        &format!(
+            "Scheduled pin {} by <@{}> was published on <#{}>",
+            pin.name, pin.author_id, channel.id
+        ),
+        &pin.content,
+    ) {
+        warn!("Unable to log scheduled pin {}: {:?}", pin.name, err);
+    }
+    Ok(())
+}
+
+fn is_gone(err: &SerenityError) -> bool {
+    match err {
+        SerenityError::Http(err) => match **err {
+            HttpError::UnsuccessfulRequest(ref response) => {
+                response.status_code == StatusCode::FORBIDDEN
+                    || response.status_code == StatusCode::NOT_FOUND
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// why a scheduled pin can never be published, as opposed to a failure worth retrying
+fn unpublishable(context: &Context, pin: &db::ScheduledPin) -> Option<(Option<GuildId>, String)> {
+    let channel = match pin.channel_id.to_channel(context) {
+        Ok(Channel::Guild(channel)) => channel,
+        Ok(_) => return Some((None, "the channel isn't on a server".to_owned())),
+        Err(ref err) if is_gone(err) => {
+            return Some((None, "the channel no longer exists".to_owned()))
+        }
+        Err(_) => return None,
+    };
+    let channel = channel.try_read_for(READ_TIMEOUT)?;
+    let uid = context
+        .cache
+        .try_read_for(READ_TIMEOUT)
+        .map(|cache| cache.user.id)?;
+    let permissions = channel.permissions_for_user(context, uid).ok()?;
+    if permissions.contains(Permissions::SEND_MESSAGES | Permissions::MANAGE_MESSAGES) {
+        None
+    } else {
+        Some((
+            Some(channel.guild_id),
+            "I'm not allowed to send and pin messages there".to_owned(),
+        ))
+    }
+}
+
+fn drop_schedule(
+    context: &Context,
+    pin: &db::ScheduledPin,
+    guild_id: Option<GuildId>,
+    reason: &str,
+) -> CommandResult {
+    warn!(
+        "Dropping scheduled pin {} in {}: {}",
+        pin.name, pin.channel_id, reason
+    );
+    db::with_db(|conn| db::delete_pin_schedule(&conn, pin.id))?;
+
+    let summary = format!(
+        "Scheduled pin {} by <@{}> on <#{}> was dropped because {}",
+        pin.name, pin.author_id, pin.channel_id, reason
+    );
+    let result = if let Some(guild_id) = guild_id {
+        log_pin(context, guild_id, None, &summary, &pin.content)
+    } else {
+        // the guild of a deleted channel is unknown, so every log channel hears about it
+        CONFIG
+            .discord
+            .log_channels
+            .iter()
+            .try_for_each(|log_channel| {
+                log_channel
+                    .send_message(context, |msg| {
+                        msg.embed(|e| {
+                            e.colour(Colour::BLUE)
+                                .description(format!("**{}**\n{}", summary, pin.content))
+                                .timestamp(&Utc::now())
+                        })
+                    })
+                    .map(|_| ())
+            })
+            .map_err(CommandError::from)
+    };
+    if let Err(err) = result {
+        warn!("Unable to log dropped pin {}: {:?}", pin.name, err);
+    }
+    Ok(())
+}
+
+// failed pins stay scheduled and are retried on the next round, unless they never can be
+fn publish_due(context: &Context) -> CommandResult {
+    for pin in db::with_db(|conn| db::get_due_pins(&conn))? {
+        if let Some((guild_id, reason)) = unpublishable(context, &pin) {
+            drop_schedule(context, &pin, guild_id, &reason)?;
+        } else if let Err(err) = publish(context, &pin) {
+            warn!(
+                "Unable to publish scheduled pin {} in {}, retrying later: {:?}",
+                pin.name, pin.channel_id, err
+            );
+        } else {
+            db::with_db(|conn| db::pin_published(&conn, pin.id))?;
+        }
+    }
+    Ok(())
+}
+
+fn expire_due(context: &Context) -> CommandResult {
+    for (channel_id, name) in db::with_db(|conn| db::get_expired_pins(&conn))? {
+        if let Some(message_id) = db::with_db(|conn| db::get_pin_message(&conn, channel_id, &name))?
+        {
+            if let Err(err) = channel_id.delete_message(context, message_id) {
+                warn!(
+                    "Unable to delete expired pin {} in {}: {:?}",
+                    name, channel_id, err
+                );
+            }
+        }
+        db::with_db(|conn| {
+            db::delete_pin(&conn, channel_id, &name)?;
+            db::unexpire_pin(&conn, channel_id, &name)
+        })?;
+
+        if let Some(channel) = channel_id.to_channel(context).ok().and_then(Channel::guild) {
+            let guild_id = channel.read().guild_id;
+            if let Err(err) = log_pin(
+                context,
+                guild_id,
+                None,
+                &format!("Pin {} expired on <#{}>", name, channel_id),
+                "",
+            ) {
+                warn!("Unable to log expired pin {}: {:?}", name, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn_pin_scheduler(context: Context) {
+    SCHEDULER.call_once(|| {
+        trace!("Spawning pin scheduler thread...");
+        let result = thread::Builder::new()
+            .name("pin_scheduler".to_owned())
+            .spawn(move || loop {
+                if let Err(err) = publish_due(&context) {
+                    error!("pin scheduler error: {:?}", err);
+                }
+                if let Err(err) = expire_due(&context) {
+                    error!("pin expiry error: {:?}", err);
+                }
+                thread::sleep(SCHEDULE_INTERVAL);
+            });
+        if let Err(err) = result {
+            error!("Error spawning pin scheduler thread: {}", err);
+        }
+    });
+}
+
+#[check]
+#[name = "Pin"]
+fn pin_check(
+    context: &mut Context,
+    message: &Message,
+    _: &mut Args,
+    _: &CommandOptions,
+) -> CheckResult {
+    // is_allowed keeps the bot quiet about this outside of pin channels
+    if !CONFIG.discord.pin_channels.contains(&message.channel_id) {
+        return CheckResult::new_log("not a pin channel");
+    }
+
+    let permission = CONFIG
+        .discord
+        .pin_permissions
+        .iter()
+        .find(|permission| permission.channel == message.channel_id);
+    match (permission, message.member(&context)) {
+        (None, _) => CheckResult::Success,
+        (Some(permission), Some(member))
+            if member
+                .roles
+                .iter()
+                .any(|role| permission.roles.contains(role)) =>
+        {
+            CheckResult::Success
+        }
+        _ => CheckResult::new_user("You're not allowed to change pins in this channel!"),
+    }
+}
 
 #[command]
-#[description("Manage the public pin on the current channel")]
-#[usage("new_text\u{2026}")]
+#[description("Manage the public pins on the current channel")]
+#[usage(
+    "[name | --name name] [--at time] [--until time] text\u{2026} | list | history [name] | revert [name] [n]"
+)]
+#[min_args(1)]
 #[only_in("guilds")]
+#[checks(Pin)]
 #[help_available(false)]
 pub fn pin(context: &mut Context, message: &Message, args: Args) -> CommandResult {
     if let Some(channel) = message.channel(&context).and_then(Channel::guild) {
-        let uid = context
-            .cache
-            .try_read_for(READ_TIMEOUT)
-            .map(|cache| cache.user.id)
-            .ok_or(SerenityError::Other("Can't lock cache"))?;
         let channel = channel
             .try_read_for(READ_TIMEOUT)
             .ok_or(SerenityError::Other("Can't lock channel"))?;
-        let pintext = args.message();
 
-        if let Some(mut pinned) = channel
-            .pins(&context)?
+        let known: Vec<String> = db::with_db(|conn| db::get_pins(&conn, channel.id))?
             .into_iter()
-            .find(|msg| msg.author.id == uid)
-        {
-            pinned.edit(&context, |edit| edit.content(pintext))?;
-        } else {
-            channel
-                .send_message(&context, |msg| msg.content(pintext))?
-                .pin(&context)?;
-        }
-
-        for log_channel in get_log_channels(&context, channel.guild_id) {
-            log_channel.send_message(&context, |msg| {
-                msg.embed(|e| {
-                    e.colour(Colour::BLUE)
-                        .description(format!(
-                            "**<@{}> changed the public pin on <#{}>**\n{}",
-                            message.author.id, channel.id, pintext
-                        ))
-                        .author(|a| {
-                            a.name(&message.author.tag())
-                                .icon_url(&message.author.face())
-                        })
-                        .timestamp(&message.timestamp)
-                })
+            .map(|(name, _message_id)| name)
+            .chain(
+                CONFIG
+                    .discord
+                    .pin_names
+                    .iter()
+                    .map(|name| name.to_lowercase()),
+            )
+            .filter(|name| !RESERVED_NAMES.contains(&name.as_str()))
+            .collect();
+        let (mut name, mut text) = split_name(args.message().trim(), &known);
+        let mut publish_at = None;
+        let mut expire_at = None;
+        loop {
+            let (word, rest) = split_word(text);
+            let slot = match word {
+                "--at" => &mut publish_at,
+                "--until" => &mut expire_at,
+                "--name" => {
+                    let (word, rest) = split_word(rest);
+                    name = Some(word.to_lowercase());
+                    text = rest;
+                    continue;
+                }
+                _ => break,
+            };
+            let (time, rest) = split_word(rest);
+            if let Some(time) = parse_time(time).filter(|time| *time > Utc::now()) {
+                *slot = Some(time);
+                text = rest;
+            } else {
+                message.reply(
+                    &context,
+                    &format!(
+                        "{} isn't a time in the future! Use YYYY-MM-DD or YYYY-MM-DDTHH:MM (UTC).",
+                        time
+                    ),
+                )?;
+                return Ok(());
+            }
+        }
+        let pintext = text;
+
+        if name.is_none() && publish_at.is_none() && expire_at.is_none() {
+            let words: Vec<&str> = pintext.split_whitespace().collect();
+            match words[..] {
+                ["list"] => return list(context, message, &channel),
+                ["history"] => return history(context, message, &channel, DEFAULT_PIN),
+                ["history", name] => {
+                    return history(context, message, &channel, &name.to_lowercase())
+                }
+                ["revert"] => return revert(context, message, &channel, DEFAULT_PIN, 1),
+                ["revert", back] if back.parse::<u32>().is_ok() => {
+                    return revert(context, message, &channel, DEFAULT_PIN, back.parse()?)
+                }
+                ["revert", name] => {
+                    return revert(context, message, &channel, &name.to_lowercase(), 1)
+                }
+                ["revert", name, back] => {
+                    return revert(
+                        context,
+                        message,
+                        &channel,
+                        &name.to_lowercase(),
+                        back.parse()?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let name = name.unwrap_or_else(|| DEFAULT_PIN.to_owned());
+        if name.is_empty() || RESERVED_NAMES.contains(&name.as_str()) {
+            message.reply(
+                &context,
+                &format!(
+                    "{} can't be the name of a pin! <:lyou:350623520494977035>",
+                    if name.is_empty() { "Nothing" } else { &name }
+                ),
+            )?;
+            return Ok(());
+        }
+        if pintext.is_empty() {
+            return Err("empty pin text".into());
+        }
+        if let (Some(publish_at), Some(expire_at)) = (publish_at, expire_at) {
+            if expire_at <= publish_at {
+                message.reply(
+                    &context,
+                    "A pin can't expire before it's published! <:lyou:350623520494977035>",
+                )?;
+                return Ok(());
+            }
+        }
+
+        let until = expire_at.map_or_else(String::new, |time| {
+            format!(" until {}", time.format("%Y-%m-%d %H:%M UTC"))
+        });
+        if let Some(publish_at) = publish_at {
+            db::with_db(|conn| {
+                db::schedule_pin(
+                    &conn,
+                    channel.id,
+                    &name,
+                    message.author.id,
+                    pintext,
+                    publish_at,
+                    expire_at,
+                )
             })?;
+            log_pin(
+                context,
+                channel.guild_id,
+                Some(&message.author),
+                &format!(
+                    "<@{}> scheduled pin {} on <#{}> for {}{}",
+                    message.author.id,
+                    name,
+                    channel.id,
+                    publish_at.format("%Y-%m-%d %H:%M UTC"),
+                    until
+                ),
+                pintext,
+            )?;
+            message.react(&context, '\u{2705}')?;
+            return Ok(());
         }
+
+        set_pin(context, &channel, &name, pintext)?;
+        db::with_db(|conn| {
+            db::pin_revision(&conn, channel.id, &name, message.author.id, pintext)?;
+            if let Some(expire_at) = expire_at {
+                db::expire_pin(&conn, channel.id, &name, expire_at)
+            } else {
+                db::unexpire_pin(&conn, channel.id, &name)
+            }
+        })?;
+        log_pin(
+            context,
+            channel.guild_id,
+            Some(&message.author),
+            &format!(
+                "<@{}> changed pin {} on <#{}>{}",
+                message.author.id, name, channel.id, until
+            ),
+            pintext,
+        )?;
     }
     Ok(())
 }
+
+#[command]
+#[description("Remove a public pin from the current channel")]
+#[usage("[name]")]
+#[max_args(1)]
+#[only_in("guilds")]
+#[checks(Pin)]
+#[help_available(false)]
+pub fn unpin(context: &mut Context, message: &Message, mut args: Args) -> CommandResult {
+    let guild_id = message
+        .guild_id
+        .ok_or(SerenityError::Other("Pins are only available on a server"))?;
+    let name = args
+        .single::<String>()
+        .map_or_else(|_| DEFAULT_PIN.to_owned(), |name| name.to_lowercase());
+
+    let message_id = if let Some(message_id) =
+        db::with_db(|conn| db::get_pin_message(&conn, message.channel_id, &name))?
+    {
+        message_id
+    } else {
+        message.reply(
+            &context,
+            &format!(
+                "There is no pin called {} here! <:lyou:350623520494977035>",
+                name
+            ),
+        )?;
+        return Ok(());
+    };
+
+    if let Err(err) = message.channel_id.delete_message(&context, message_id) {
+        warn!(
+            "Unable to delete pin {} in {}: {:?}",
+            name, message.channel_id, err
+        );
+    }
+    db::with_db(|conn| {
+        db::delete_pin(&conn, message.channel_id, &name)?;
+        db::unexpire_pin(&conn, message.channel_id, &name)?;
+        db::unschedule_pin(&conn, message.channel_id, &name)
+    })?;
+
+    log_pin(
+        context,
+        guild_id,
+        Some(&message.author),
+        &format!(
+            "<@{}> removed pin {} from <#{}>",
+            message.author.id, name, message.channel_id
+        ),
+        "",
+    )?;
+    message.react(&context, '\u{2705}')?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_is_not_an_embed() {
+        assert!(PinEmbed::parse("just some text\nover two lines").is_none());
+    }
+
+    #[test]
+    fn embed_markup() {
+        let embed = PinEmbed::parse(
+            "# Rules\ncolour: #ff8800\nBe nice.\n## Spoilers\nUse tags.\nAlways.\n## Empty",
+        )
+        .unwrap();
+        assert_eq!(embed.title.as_deref(), Some("Rules"));
+        assert_eq!(embed.colour, Some(Colour::new(0x00ff_8800)));
+        assert_eq!(embed.description, vec!["Be nice."]);
+        assert_eq!(
+            embed.fields,
+            vec![
+                (
+                    "Spoilers".to_owned(),
+                    vec!["Use tags.".to_owned(), "Always.".to_owned()]
+                ),
+                ("Empty".to_owned(), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn embed_colour_spellings() {
+        assert_eq!(
+            PinEmbed::parse("color: 00ff00").unwrap().colour,
+            Some(Colour::new(0x0000_ff00))
+        );
+        // not a colour, so it's neither markup nor an embed
+        assert!(PinEmbed::parse("colour: mauve").is_none());
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(
+            parse_time("2026-11-01"),
+            Some(Utc.ymd(2026, 11, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            parse_time("2026-11-01T18:30"),
+            Some(Utc.ymd(2026, 11, 1).and_hms(18, 30, 0))
+        );
+        assert_eq!(
+            parse_time("2026-11-01T18:30:00+02:00"),
+            Some(Utc.ymd(2026, 11, 1).and_hms(16, 30, 0))
+        );
+    }
+
+    #[test]
+    fn invalid_times() {
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("tomorrow"), None);
+        assert_eq!(parse_time("2026-13-01"), None);
+        assert_eq!(parse_time("2026-11-01T25:00"), None);
+    }
+
+    #[test]
+    fn pin_names() {
+        let known = vec!["rules".to_owned()];
+        assert_eq!(
+            split_name("Rules be nice", &known),
+            (Some("rules".to_owned()), "be nice")
+        );
+        assert_eq!(split_name("rules", &known), (None, "rules"));
+        assert_eq!(split_name("hello there", &known), (None, "hello there"));
+    }
+}
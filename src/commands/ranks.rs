@@ -1,6 +1,10 @@
 use super::READ_TIMEOUT;
-use crate::CONFIG;
-use log::trace;
+use crate::{
+    config::{RankCategoryConfig, RankRequirementsConfig},
+    db, util, CONFIG,
+};
+use chrono::{Duration, Utc};
+use log::{trace, warn};
 use serenity::{
     framework::standard::{macros::command, Args, CommandResult},
     model::prelude::*,
@@ -8,6 +12,10 @@ use serenity::{
     utils::Colour,
 };
 
+const MAX_TYPO_DISTANCE: usize = 2;
+const MEMBERS_PER_PAGE: usize = 20;
+const TREND_DAYS: u32 = 30;
+
 fn get_ranks<'a>(
     context: &Context,
     guild: &'a Guild,
@@ -50,7 +58,11 @@ fn get_ranks<'a>(
     let ranks: Vec<_> = guild
         .roles
         .values()
-        .filter(|role| !role.name.starts_with('@') && role.position < max_pos)
+        .filter(|role| {
+            !role.name.starts_with('@')
+                && role.position < max_pos
+                && !CONFIG.ranks.excluded.contains(&role.id)
+        })
         .collect();
     trace!("Found {} ranks", ranks.len());
 
@@ -74,6 +86,191 @@ fn get_ranks<'a>(
         .collect())
 }
 
+fn rank_category(rank: RoleId) -> Option<&'static RankCategoryConfig> {
+    CONFIG
+        .ranks
+        .categories
+        .iter()
+        .find(|category| category.roles.contains(&rank))
+}
+
+// ranks that joining rank_id takes away because they share an exclusive category
+fn replaced_ranks(member: &Member, rank_id: RoleId) -> Vec<RoleId> {
+    rank_category(rank_id)
+        .filter(|category| category.exclusive)
+        .map_or_else(Vec::new, |category| {
+            member
+                .roles
+                .iter()
+                .filter(|id| **id != rank_id && category.roles.contains(id))
+                .copied()
+                .collect()
+        })
+}
+
+fn add_rank(
+    context: &Context,
+    member: &mut Member,
+    rank_id: RoleId,
+) -> Result<Vec<RoleId>, SerenityError> {
+    let replaced = replaced_ranks(member, rank_id);
+    if !replaced.is_empty() {
+        member.remove_roles(context, &replaced)?;
+    }
+    member.add_role(context, rank_id)?;
+    let user_id = member.user_id();
+    let _ = db::with_db(|conn| {
+        for id in &replaced {
+            db::rank_changed(&conn, user_id, *id)?;
+        }
+        db::rank_changed(&conn, user_id, rank_id)
+    });
+    Ok(replaced)
+}
+
+fn remove_rank(
+    context: &Context,
+    member: &mut Member,
+    rank_id: RoleId,
+) -> Result<(), SerenityError> {
+    member.remove_role(context, rank_id)?;
+    let _ = db::with_db(|conn| db::rank_changed(&conn, member.user_id(), rank_id));
+    Ok(())
+}
+
+fn rank_requirements(rank: RoleId) -> Option<&'static RankRequirementsConfig> {
+    CONFIG
+        .ranks
+        .requirements
+        .iter()
+        .find(|requirements| requirements.rank == rank)
+}
+
+fn cooldown_remaining(user_id: UserId, rank_id: RoleId) -> Option<Duration> {
+    let cooldown = rank_requirements(rank_id)?.cooldown?;
+    let changed = db::with_db(|conn| db::get_rank_change(&conn, user_id, rank_id))
+        .ok()
+        .flatten()?;
+    let remaining =
+        Duration::minutes(cooldown) - Utc::now().naive_utc().signed_duration_since(changed);
+    if remaining > Duration::zero() {
+        Some(remaining)
+    } else {
+        None
+    }
+}
+
+fn unmet_requirement(
+    member: &Member,
+    ranks: &[(RoleId, String)],
+    rank_id: RoleId,
+    joining: bool,
+) -> Option<String> {
+    let user_id = member.user_id();
+
+    if let Some(remaining) = cooldown_remaining(user_id, rank_id) {
+        return Some(format!(
+            "you changed it too recently, try again in {}",
+            util::format_duration(remaining)
+        ));
+    }
+
+    if !joining {
+        return None;
+    }
+
+    // an exclusive swap leaves the old rank, which has to respect its cooldown too
+    for replaced in replaced_ranks(member, rank_id) {
+        if let Some(remaining) = cooldown_remaining(user_id, replaced) {
+            return Some(format!(
+                "you changed **{}** too recently, try again in {}",
+                ranks
+                    .iter()
+                    .find(|(id, _name)| *id == replaced)
+                    .map_or_else(|| replaced.to_string(), |(_id, name)| name.clone()),
+                util::format_duration(remaining)
+            ));
+        }
+    }
+
+    let requirements = rank_requirements(rank_id)?;
+
+    if let Some(days) = requirements.min_account_age {
+        if util::account_age(user_id) < Duration::days(days) {
+            return Some(format!(
+                "your account needs to be at least {} days old",
+                days
+            ));
+        }
+    }
+
+    if let Some(days) = requirements.min_member_age {
+        let first_message = db::with_db(|conn| db::get_first_message(&conn, user_id))
+            .ok()
+            .flatten();
+        if first_message.map_or(true, |first| {
+            Utc::now().naive_utc().signed_duration_since(first) < Duration::days(days)
+        }) {
+            return Some(format!(
+                "you need to have been chatting here for at least {} days",
+                days
+            ));
+        }
+    }
+
+    let missing: Vec<String> = requirements
+        .requires
+        .iter()
+        .filter(|id| !member.roles.contains(id))
+        .map(|id| format!("<@&{}>", id))
+        .collect();
+    if !missing.is_empty() {
+        return Some(format!("you need {} first", missing.join(" and ")));
+    }
+
+    None
+}
+
+fn emoji_key(emoji: &ReactionType) -> String {
+    match emoji {
+        ReactionType::Custom { id, .. } => id.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_emoji(text: &str) -> ReactionType {
+    if let Ok(custom) = text.parse::<EmojiIdentifier>() {
+        ReactionType::Custom {
+            animated: text.starts_with("<a:"),
+            id: custom.id,
+            name: Some(custom.name),
+        }
+    } else {
+        ReactionType::Unicode(text.to_owned())
+    }
+}
+
+fn format_rank_list(ranks: &[&(&Role, Vec<&Member>)]) -> String {
+    let longest_name = ranks
+        .iter()
+        .map(|(rank, _members)| rank.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut desc_lines: Vec<String> = ranks
+        .iter()
+        .map(|(rank, members)| {
+            format!(
+                "{:w$}{:3}",
+                format!("{}:", rank.name),
+                members.len(),
+                w = longest_name + 1
+            )
+        })
+        .collect();
+    desc_lines.sort();
+    format!("```ldif\n{}```", desc_lines.join("\n"))
+}
+
 #[command]
 #[description("List all available ranks, as well as the requesting user's active ones")]
 #[num_args(0)]
@@ -87,26 +284,41 @@ pub fn ranks(context: &mut Context, message: &Message, _: Args) -> CommandResult
         if ranks.is_empty() {
             (Some("There are no ranks on the server!".to_owned()), None)
         } else {
-            let rank_text = {
-                let longest_name = ranks
-                    .iter()
-                    .map(|(rank, _members)| rank.name.len())
-                    .max()
-                    .expect("Impossible empty list");
-                let mut desc_lines: Vec<String> = ranks
-                    .iter()
-                    .map(|(rank, members)| {
+            let mut groups: Vec<(String, Vec<_>)> = CONFIG
+                .ranks
+                .categories
+                .iter()
+                .map(|category| {
+                    (
                         format!(
-                            "{:w$}{:3}",
-                            format!("{}:", rank.name),
-                            members.len(),
-                            w = longest_name + 1
-                        )
-                    })
-                    .collect();
-                desc_lines.sort();
-                Some(format!("```ldif\n{}```", desc_lines.join("\n")))
-            };
+                            "{} (pick {})",
+                            category.name,
+                            if category.exclusive { "one" } else { "many" }
+                        ),
+                        ranks
+                            .iter()
+                            .filter(|(rank, _members)| category.roles.contains(&rank.id))
+                            .collect(),
+                    )
+                })
+                .collect();
+            groups.push((
+                if CONFIG.ranks.categories.is_empty() {
+                    "Ranks"
+                } else {
+                    "Other"
+                }
+                .to_owned(),
+                ranks
+                    .iter()
+                    .filter(|(rank, _members)| rank_category(rank.id).is_none())
+                    .collect(),
+            ));
+            let rank_text: Vec<(String, String)> = groups
+                .into_iter()
+                .filter(|(_name, group)| !group.is_empty())
+                .map(|(name, group)| (name, format_rank_list(&group)))
+                .collect();
             let reply = guild.members.get(&message.author.id).and_then(|user| {
                 let mut rank_names: Vec<String> = ranks
                     .into_iter()
@@ -125,7 +337,7 @@ pub fn ranks(context: &mut Context, message: &Message, _: Args) -> CommandResult
                     Some(format!("Your current ranks are {}", rank_names.join(", ")))
                 }
             });
-            (reply, rank_text)
+            (reply, Some(rank_text))
         }
     } else {
         (
@@ -138,8 +350,17 @@ pub fn ranks(context: &mut Context, message: &Message, _: Args) -> CommandResult
             msg.embed(|e| {
                 e.colour(Colour::BLUE)
                     .title("Available ranks")
-                    .description(rank_text)
-                    .footer(|f| f.text(format!("Use the {0}join and {0}leave commands to change your ranks", CONFIG.discord.command_prefix)))
+                    .fields(
+                        rank_text
+                            .into_iter()
+                            .map(|(name, list)| (name, list, false)),
+                    )
+                    .footer(|f| {
+                        f.text(format!(
+                            "Use the {0}join and {0}leave commands to change your ranks, or {0}rank info for details",
+                            CONFIG.discord.command_prefix
+                        ))
+                    })
             })
         })?;
     }
@@ -149,85 +370,438 @@ pub fn ranks(context: &mut Context, message: &Message, _: Args) -> CommandResult
     Ok(())
 }
 
+enum RankMatch<'a> {
+    Found(RoleId, &'a str),
+    Suggestion(&'a str),
+    NotFound,
+}
+
+fn find_rank<'a>(ranks: &'a [(RoleId, String)], name: &str) -> RankMatch<'a> {
+    let name = name.to_lowercase();
+    if let Some((id, rank)) = ranks.iter().find(|(_id, rank)| rank.to_lowercase() == name) {
+        return RankMatch::Found(*id, rank);
+    }
+    // short names are only a few typos away from each other
+    let max_distance = (name.chars().count() / 3).min(MAX_TYPO_DISTANCE);
+    ranks
+        .iter()
+        .map(|(_id, rank)| (util::edit_distance(&rank.to_lowercase(), &name), rank))
+        .filter(|(distance, _rank)| *distance <= max_distance)
+        .min_by_key(|(distance, _rank)| *distance)
+        .map_or(RankMatch::NotFound, |(_distance, rank)| {
+            RankMatch::Suggestion(rank)
+        })
+}
+
+fn joinleave_one(
+    context: &Context,
+    user: &mut Member,
+    ranks: &[(RoleId, String)],
+    rank_id: RoleId,
+    rankname: &str,
+    should_be_current: Option<bool>,
+) -> Result<String, SerenityError> {
+    let is_current = user.roles.contains(&rank_id);
+    Ok(
+        if should_be_current.map_or(false, |should| should && !is_current) {
+            format!(
+                "You aren't even in **{}**! <:lyou:350623520494977035>",
+                rankname
+            )
+        } else if should_be_current.map_or(false, |should| !should && is_current) {
+            format!(
+                "You are already in **{}**! <:lyou:350623520494977035>",
+                rankname
+            )
+        } else if let Some(reason) = unmet_requirement(user, ranks, rank_id, !is_current) {
+            format!(
+                "You can't {} **{}**: {}. <:lyou:350623520494977035>",
+                if is_current { "leave" } else { "join" },
+                rankname,
+                reason
+            )
+        } else if is_current {
+            remove_rank(context, user, rank_id)?;
+            format!("You have left **{}**! <:aj05:310579190770434050>", rankname)
+        } else {
+            let replaced = add_rank(context, user, rank_id)?;
+            if replaced.is_empty() {
+                format!(
+                    "You have joined **{}**! <:twiyay:310582814565466112>",
+                    rankname
+                )
+            } else {
+                format!(
+                    "You have joined **{}** and left **{}**! <:twiyay:310582814565466112>",
+                    rankname,
+                    ranks
+                        .iter()
+                        .filter(|(id, _name)| replaced.contains(id))
+                        .map(|(_id, name)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join("**, **")
+                )
+            }
+        },
+    )
+}
+
 fn joinleave(
     context: &mut Context,
     message: &Message,
     args: Args,
     should_be_current: Option<bool>,
 ) -> CommandResult {
-    let rankname = args.message().trim();
-    let response = if let Some(guild) = message.guild(&context) {
+    let requested: Vec<&str> = args
+        .message()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut lines: Vec<String> = Vec::new();
+    if let Some(guild) = message.guild(&context) {
         let mut guild = guild.write();
-        if let Some(rank_id) =
-            get_ranks(&context, &guild)?
-                .into_iter()
-                .find_map(|(rank, _members)| {
-                    if rank.name.to_lowercase() == rankname.to_lowercase() {
-                        Some(rank.id)
-                    } else {
-                        None
+        let ranks: Vec<(RoleId, String)> = get_ranks(&context, &guild)?
+            .into_iter()
+            .map(|(rank, _members)| (rank.id, rank.name.clone()))
+            .collect();
+        if let Some(user) = guild.members.get_mut(&message.author.id) {
+            if should_be_current == Some(true)
+                && requested.len() == 1
+                && requested[0].eq_ignore_ascii_case("all")
+            {
+                for (rank_id, rankname) in &ranks {
+                    if user.roles.contains(rank_id) {
+                        lines.push(joinleave_one(
+                            &context,
+                            user,
+                            &ranks,
+                            *rank_id,
+                            rankname,
+                            should_be_current,
+                        )?);
                     }
-                })
-        {
-            if let Some(user) = guild.members.get_mut(&message.author.id) {
-                let is_current = user.roles.contains(&rank_id);
-                if should_be_current.map_or(false, |should| should && !is_current) {
-                    format!(
-                        "You aren't even in **{}**! <:lyou:350623520494977035>",
-                        rankname
-                    )
-                } else if should_be_current.map_or(false, |should| !should && is_current) {
-                    format!(
-                        "You are already in **{}**! <:lyou:350623520494977035>",
-                        rankname
-                    )
-                } else if is_current {
-                    user.remove_role(&context, rank_id)?;
-                    format!("You have left **{}**! <:aj05:310579190770434050>", rankname)
-                } else {
-                    user.add_role(&context, rank_id)?;
-                    format!(
-                        "You have joined **{}**! <:twiyay:310582814565466112>",
-                        rankname
-                    )
+                }
+                if lines.is_empty() {
+                    lines.push("You don't have any ranks! <:lyou:350623520494977035>".to_owned());
                 }
             } else {
-                "You are not on the server? WTF?".to_owned()
+                for rankname in requested {
+                    lines.push(match find_rank(&ranks, rankname) {
+                        RankMatch::Found(rank_id, rankname) => joinleave_one(
+                            &context,
+                            user,
+                            &ranks,
+                            rank_id,
+                            rankname,
+                            should_be_current,
+                        )?,
+                        RankMatch::Suggestion(suggestion) => format!(
+                            "There is no rank called **{}**. Did you mean **{}**?",
+                            rankname, suggestion
+                        ),
+                        RankMatch::NotFound => format!(
+                            "There is no rank called **{}**. <:lyou:350623520494977035>",
+                            rankname
+                        ),
+                    });
+                }
             }
         } else {
-            "There is no such rank. <:lyou:350623520494977035>".to_owned()
+            lines.push("You are not on the server? WTF?".to_owned());
         }
     } else {
-        "Rank joining/leaving is only available on a server!".to_owned()
-    };
-    message.reply(&context, &response)?;
+        lines.push("Rank joining/leaving is only available on a server!".to_owned());
+    }
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::BLUE)
+                .author(|a| {
+                    a.name(&message.author.tag())
+                        .icon_url(&message.author.face())
+                })
+                .description(lines.join("\n"))
+        })
+    })?;
     Ok(())
 }
 
 #[command]
-#[description("Join/leave a rank")]
-#[usage("rankname")]
-#[num_args(1)]
+#[description("Join/leave ranks")]
+#[usage("rankname[, rankname\u{2026}]")]
+#[min_args(1)]
 #[only_in("guilds")]
 #[help_available(false)]
+#[sub_commands(rank_info)]
 pub fn rank(context: &mut Context, message: &Message, args: Args) -> CommandResult {
     joinleave(context, message, args, None)
 }
 
 #[command]
-#[description("Join a rank")]
-#[usage("rankname")]
-#[num_args(1)]
+#[description("Join ranks")]
+#[usage("rankname[, rankname\u{2026}]")]
+#[min_args(1)]
 #[only_in("guilds")]
 pub fn join(context: &mut Context, message: &Message, args: Args) -> CommandResult {
     joinleave(context, message, args, Some(false))
 }
 
 #[command]
-#[description("Leave a rank")]
-#[usage("rankname")]
-#[num_args(1)]
+#[description("Leave ranks, or all of them at once")]
+#[usage("rankname[, rankname\u{2026}] | all")]
+#[min_args(1)]
 #[only_in("guilds")]
 pub fn leave(context: &mut Context, message: &Message, args: Args) -> CommandResult {
     joinleave(context, message, args, Some(true))
 }
+
+#[command]
+#[description("Post a message that grants ranks to everyone who reacts to it")]
+#[usage("emoji rankname, emoji rankname\u{2026}")]
+#[min_args(2)]
+#[only_in("guilds")]
+#[required_permissions(MANAGE_ROLES)]
+pub fn rankmenu(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let guild = message.guild(&context).ok_or(SerenityError::Other(
+        "Rank menus are only available on a server",
+    ))?;
+    let mut bindings: Vec<(ReactionType, RoleId, String)> = Vec::new();
+    {
+        let guild = guild
+            .try_read_for(READ_TIMEOUT)
+            .ok_or(SerenityError::Other("Unable to lock guild"))?;
+        let ranks = get_ranks(&context, &guild)?;
+        for entry in args.message().split(',') {
+            let mut parts = entry.trim().splitn(2, char::is_whitespace);
+            let emoji = parts.next().unwrap_or_default();
+            let rankname = parts.next().unwrap_or_default().trim();
+            if let Some((rank, _members)) = ranks
+                .iter()
+                .find(|(rank, _members)| rank.name.to_lowercase() == rankname.to_lowercase())
+            {
+                bindings.push((parse_emoji(emoji), rank.id, rank.name.clone()));
+            } else {
+                message.reply(
+                    &context,
+                    &format!(
+                        "There is no rank called **{}**. <:lyou:350623520494977035>",
+                        rankname
+                    ),
+                )?;
+                return Ok(());
+            }
+        }
+    }
+
+    let menu = message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(Colour::BLUE)
+                .title("Pick your ranks")
+                .description(
+                    bindings
+                        .iter()
+                        .map(|(emoji, _id, name)| format!("{} **{}**", emoji, name))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+                .footer(|f| f.text("React to join a rank, remove the reaction to leave it"))
+        })
+    })?;
+
+    // only bind emoji that reacting with actually worked for
+    for (emoji, role_id, _name) in bindings {
+        let key = emoji_key(&emoji);
+        menu.react(&context, emoji)?;
+        db::with_db(|conn| db::add_rank_reaction(&conn, menu.channel_id, menu.id, &key, role_id))?;
+    }
+    Ok(())
+}
+
+fn apply_rank_reaction(
+    context: &Context,
+    reaction: &Reaction,
+    rank_id: RoleId,
+    added: bool,
+) -> Result<(), SerenityError> {
+    let uid = context
+        .cache
+        .try_read_for(READ_TIMEOUT)
+        .map(|cache| cache.user.id)
+        .ok_or(SerenityError::Other("Can't lock cache"))?;
+    if reaction.user_id == uid {
+        return Ok(());
+    }
+
+    let guild_id = reaction
+        .guild_id
+        .ok_or(SerenityError::Other("Rank reaction outside of a guild"))?;
+    let guild = guild_id
+        .to_guild_cached(context)
+        .ok_or(SerenityError::Other("Can't find guild for rank reaction"))?;
+    let ranks: Vec<(RoleId, String)> = {
+        let guild = guild
+            .try_read_for(READ_TIMEOUT)
+            .ok_or(SerenityError::Other("Unable to lock guild"))?;
+        get_ranks(context, &guild)?
+            .into_iter()
+            .map(|(rank, _members)| (rank.id, rank.name.clone()))
+            .collect()
+    };
+    if !ranks.iter().any(|(id, _name)| *id == rank_id) {
+        return Ok(());
+    }
+
+    let mut member = guild_id.member(context, reaction.user_id)?;
+    let is_current = member.roles.contains(&rank_id);
+    if added == is_current {
+        return Ok(());
+    }
+
+    if let Some(reason) = unmet_requirement(&member, &ranks, rank_id, added) {
+        if added {
+            reaction.delete(context)?;
+        }
+        let rankname = rank_id
+            .to_role_cached(context)
+            .map_or_else(|| rank_id.to_string(), |role| role.name);
+        // a removed reaction can't be put back for the user, so ask them to
+        reaction.user_id.create_dm_channel(context)?.say(
+            context,
+            format!(
+                "You can't {} **{}**: {}.{}",
+                if added { "join" } else { "leave" },
+                rankname,
+                reason,
+                if added {
+                    ""
+                } else {
+                    " You still have the rank, so react again to keep the menu in sync."
+                }
+            ),
+        )?;
+    } else if added {
+        add_rank(context, &mut member, rank_id)?;
+    } else {
+        remove_rank(context, &mut member, rank_id)?;
+    }
+    Ok(())
+}
+
+pub fn handle_rank_reaction(context: &Context, reaction: &Reaction, added: bool) {
+    if let Ok(Some(rank_id)) = db::with_db(|conn| {
+        db::get_rank_reaction(&conn, reaction.message_id, &emoji_key(&reaction.emoji))
+    }) {
+        if let Err(err) = apply_rank_reaction(context, reaction, rank_id, added) {
+            warn!("Unable to update rank from a reaction: {:?}", err);
+        }
+    }
+}
+
+#[command("info")]
+#[description("Show the members and membership history of a rank")]
+#[usage("rankname [page]")]
+#[min_args(1)]
+#[only_in("guilds")]
+pub fn rank_info(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let guild = message.guild(&context).ok_or(SerenityError::Other(
+        "Rank info is only available on a server",
+    ))?;
+    let guild = guild
+        .try_read_for(READ_TIMEOUT)
+        .ok_or(SerenityError::Other("Unable to lock guild"))?;
+
+    let ranks = get_ranks(&context, &guild)?;
+    let names: Vec<(RoleId, String)> = ranks
+        .iter()
+        .map(|(rank, _members)| (rank.id, rank.name.clone()))
+        .collect();
+
+    // a rank name can end in a number too, so only split off a page if the whole text isn't one
+    let text = args.message().trim();
+    let is_rank = names
+        .iter()
+        .any(|(_id, name)| name.to_lowercase() == text.to_lowercase());
+    let (rankname, page) = match text.rsplitn(2, ' ').collect::<Vec<_>>()[..] {
+        [page, rankname] if !is_rank && page.parse::<usize>().is_ok() => {
+            (rankname.trim(), page.parse::<usize>().unwrap_or(1))
+        }
+        _ => (text, 1),
+    };
+    let (rank, members) = match find_rank(&names, rankname) {
+        RankMatch::Found(rank_id, _name) => ranks
+            .iter()
+            .find(|(rank, _members)| rank.id == rank_id)
+            .ok_or(SerenityError::Other("Rank disappeared"))?,
+        RankMatch::Suggestion(suggestion) => {
+            message.reply(
+                &context,
+                &format!(
+                    "There is no rank called **{}**. Did you mean **{}**?",
+                    rankname, suggestion
+                ),
+            )?;
+            return Ok(());
+        }
+        RankMatch::NotFound => {
+            message.reply(
+                &context,
+                "There is no such rank. <:lyou:350623520494977035>",
+            )?;
+            return Ok(());
+        }
+    };
+
+    let mut member_names: Vec<String> = members
+        .iter()
+        .map(|member| member.display_name().into_owned())
+        .collect();
+    member_names.sort_by_key(|name| name.to_lowercase());
+    let pages = ((member_names.len() + MEMBERS_PER_PAGE - 1) / MEMBERS_PER_PAGE).max(1);
+    let page = page.max(1).min(pages);
+    let page_names: Vec<&str> = member_names
+        .iter()
+        .skip((page - 1) * MEMBERS_PER_PAGE)
+        .take(MEMBERS_PER_PAGE)
+        .map(String::as_str)
+        .collect();
+
+    let history = db::with_db(|conn| db::get_role_snapshots(&conn, rank.id, TREND_DAYS))?;
+    let created = rank.id.created_at();
+
+    message.channel_id.send_message(&context, |msg| {
+        msg.embed(|e| {
+            e.colour(rank.colour)
+                .title(&rank.name)
+                .description(if page_names.is_empty() {
+                    "Nobody has this rank.".to_owned()
+                } else {
+                    page_names.join("\n")
+                })
+                .field(
+                    "Created",
+                    format!(
+                        "{} ({} ago)",
+                        created.format("%Y-%m-%d"),
+                        util::format_duration(Utc::now().signed_duration_since(created))
+                    ),
+                    true,
+                )
+                .field("Members", members.len(), true)
+                .field(
+                    format!("Last {} days", TREND_DAYS),
+                    match (history.first(), history.last()) {
+                        (Some(first), Some(last)) if history.len() > 1 => format!(
+                            "`{}` {} \u{2192} {}",
+                            util::sparkline(&history),
+                            first,
+                            last
+                        ),
+                        _ => "Not enough data yet".to_owned(),
+                    },
+                    true,
+                )
+                .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+        })
+    })?;
+    Ok(())
+}
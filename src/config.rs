@@ -9,6 +9,7 @@ use std::{
     path::Path,
 };
 use toml;
+use url::Url;
 
 error_chain! {
     foreign_links {
@@ -26,6 +27,10 @@ pub struct Config {
     pub subreddits: HashMap<SubstitutingString, SubredditConfig>,
     pub bulk: BulkConfig,
     pub gib: GibConfig,
+    pub feeds: FeedsConfig,
+    pub raid: RaidConfig,
+    pub modmail: ModmailConfig,
+    pub ranks: RanksConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,15 +50,26 @@ pub struct DiscordConfig {
     pub command_prefix: SubstitutingString,
     pub deleted_msg_cache: u32,
     pub long_msg_threshold: usize,
+    pub new_account_threshold: i64,
     pub token: SubstitutingString,
     pub owners: HashSet<UserId>,
     pub log_channels: HashSet<ChannelId>,
     pub channel_blacklist: HashSet<ChannelId>,
     pub channel_whitelist: HashSet<ChannelId>,
     pub pin_channels: HashSet<ChannelId>,
+    #[serde(default)]
+    pub pin_names: HashSet<String>,
+    #[serde(default)]
+    pub pin_permissions: Vec<PinPermissionConfig>,
     pub sticky_roles: HashSet<RoleId>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PinPermissionConfig {
+    pub channel: ChannelId,
+    pub roles: HashSet<RoleId>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RedditConfig {
     pub enabled: bool,
@@ -76,12 +92,109 @@ pub struct BulkConfig {
 
 #[derive(Debug, Deserialize)]
 pub struct GibConfig {
-    pub filter: u32,
+    pub default_source: String,
+    pub block_nsfw_role: Option<RoleId>,
     pub history: u32,
+    #[serde(default)]
+    pub seen_scope: GibSeenScope,
     pub not_found: Vec<SubstitutingString>,
+    pub sources: HashMap<String, ImageSourceConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GibSeenScope {
+    Channel,
+    User,
+}
+
+impl Default for GibSeenScope {
+    fn default() -> Self {
+        Self::Channel
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BooruApi {
+    Philomena,
+    Danbooru,
+}
+
+impl Default for BooruApi {
+    fn default() -> Self {
+        Self::Philomena
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImageSourceConfig {
+    #[serde(default)]
+    pub api: BooruApi,
+    pub url: Url,
+    pub filter: Option<u32>,
+    pub nsfw_filter: Option<u32>,
+    #[serde(default)]
     pub aliases: HashMap<String, HashSet<String>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct FeedsConfig {
+    pub enabled: bool,
+    pub check_interval: u64,
+    #[serde(default)]
+    pub subscriptions: HashMap<String, FeedConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedConfig {
+    pub channel: ChannelId,
+    pub source: Option<String>,
+    pub query: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RaidConfig {
+    pub enabled: bool,
+    pub join_limit: usize,
+    pub join_window: u64,
+    pub lockdown_duration: u64,
+    pub quarantine_role: RoleId,
+    pub notify_roles: HashSet<RoleId>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModmailConfig {
+    pub enabled: bool,
+    pub channel: ChannelId,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RanksConfig {
+    pub excluded: HashSet<RoleId>,
+    #[serde(default)]
+    pub categories: Vec<RankCategoryConfig>,
+    #[serde(default)]
+    pub requirements: Vec<RankRequirementsConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankCategoryConfig {
+    pub name: String,
+    pub exclusive: bool,
+    pub roles: HashSet<RoleId>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankRequirementsConfig {
+    pub rank: RoleId,
+    pub min_account_age: Option<i64>,
+    pub min_member_age: Option<i64>,
+    #[serde(default)]
+    pub requires: HashSet<RoleId>,
+    pub cooldown: Option<i64>,
+}
+
 impl Config {
     pub fn from_file<P>(path: P) -> Result<Self>
     where
@@ -10,12 +10,27 @@ fn migrate_step(conn: &Connection, step: u32) -> Result<()> {
         2 => conn.execute_batch(include_str!("migrations/2.sql"))?,
         3 => conn.execute_batch(include_str!("migrations/3.sql"))?,
         4 => conn.execute_batch(include_str!("migrations/4.sql"))?,
+        5 => conn.execute_batch(include_str!("migrations/5.sql"))?,
+        6 => conn.execute_batch(include_str!("migrations/6.sql"))?,
+        7 => conn.execute_batch(include_str!("migrations/7.sql"))?,
+        8 => conn.execute_batch(include_str!("migrations/8.sql"))?,
+        9 => conn.execute_batch(include_str!("migrations/9.sql"))?,
+        10 => conn.execute_batch(include_str!("migrations/10.sql"))?,
+        11 => conn.execute_batch(include_str!("migrations/11.sql"))?,
+        12 => conn.execute_batch(include_str!("migrations/12.sql"))?,
+        13 => conn.execute_batch(include_str!("migrations/13.sql"))?,
+        14 => conn.execute_batch(include_str!("migrations/14.sql"))?,
+        15 => conn.execute_batch(include_str!("migrations/15.sql"))?,
+        16 => conn.execute_batch(include_str!("migrations/16.sql"))?,
+        17 => conn.execute_batch(include_str!("migrations/17.sql"))?,
+        18 => conn.execute_batch(include_str!("migrations/18.sql"))?,
+        19 => conn.execute_batch(include_str!("migrations/19.sql"))?,
         _ => unreachable!(),
     }
     Ok(())
 }
 
-const MIGRATION_STEPS: u32 = 5;
+const MIGRATION_STEPS: u32 = 20;
 
 pub fn apply_migrations(conn: &Connection) -> Result<(u32, u32)> {
     let initial: u32 = conn.query_row(
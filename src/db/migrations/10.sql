@@ -0,0 +1,10 @@
+BEGIN;
+
+CREATE TABLE role_snapshots (
+    role_id TEXT NOT NULL,
+    date TEXT NOT NULL DEFAULT CURRENT_DATE,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (role_id, date)
+) WITHOUT ROWID;
+
+COMMIT;
@@ -0,0 +1,13 @@
+BEGIN;
+
+CREATE TABLE pin_revisions (
+    id INTEGER PRIMARY KEY NOT NULL,
+    channel_id TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX pin_revisions_channel ON pin_revisions (channel_id, id);
+
+COMMIT;
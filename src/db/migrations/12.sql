@@ -0,0 +1,15 @@
+BEGIN;
+
+CREATE TABLE pins (
+    channel_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    PRIMARY KEY (channel_id, name)
+) WITHOUT ROWID;
+
+ALTER TABLE pin_revisions ADD COLUMN name TEXT NOT NULL DEFAULT 'pin';
+
+DROP INDEX pin_revisions_channel;
+CREATE INDEX pin_revisions_channel ON pin_revisions (channel_id, name, id);
+
+COMMIT;
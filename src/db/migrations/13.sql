@@ -0,0 +1,14 @@
+BEGIN;
+
+CREATE TABLE pin_schedule (
+    id INTEGER PRIMARY KEY NOT NULL,
+    channel_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    publish_at TEXT DEFAULT NULL,
+    expire_at TEXT DEFAULT NULL,
+    UNIQUE (channel_id, name)
+);
+
+COMMIT;
@@ -0,0 +1,16 @@
+BEGIN;
+
+CREATE TABLE gib_seen_new (
+    source TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source, id)
+) WITHOUT ROWID;
+
+INSERT INTO gib_seen_new (source, id, time)
+SELECT 'derpibooru', id, time FROM gib_seen;
+
+DROP TABLE gib_seen;
+ALTER TABLE gib_seen_new RENAME TO gib_seen;
+
+COMMIT;
@@ -0,0 +1,24 @@
+BEGIN;
+
+-- the old history can't be attributed to a channel or user, so it isn't kept
+DROP TABLE gib_seen;
+
+CREATE TABLE gib_seen (
+    source TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source, scope, id)
+) WITHOUT ROWID;
+
+CREATE INDEX gib_seen_time ON gib_seen (source, scope, time);
+
+CREATE TABLE gib_queries (
+    source TEXT NOT NULL,
+    query TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    last_served TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source, query)
+) WITHOUT ROWID;
+
+COMMIT;
@@ -0,0 +1,12 @@
+BEGIN;
+
+CREATE TABLE gib_aliases (
+    source TEXT NOT NULL,
+    alias TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source, alias)
+) WITHOUT ROWID;
+
+COMMIT;
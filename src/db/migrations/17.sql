@@ -0,0 +1,19 @@
+BEGIN;
+
+CREATE TABLE feeds (
+    name TEXT PRIMARY KEY NOT NULL,
+    source TEXT,
+    query TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) WITHOUT ROWID;
+
+CREATE TABLE feed_seen (
+    feed TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (feed, id)
+) WITHOUT ROWID;
+
+COMMIT;
@@ -0,0 +1,31 @@
+BEGIN;
+
+CREATE TABLE roll_macros (
+    owner_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    expression TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (owner_id, name)
+) WITHOUT ROWID;
+
+CREATE TABLE rolls (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    guild_id TEXT,
+    expression TEXT NOT NULL,
+    total REAL NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX rolls_user ON rolls (user_id, time);
+
+CREATE TABLE roll_faces (
+    user_id TEXT NOT NULL,
+    sides INTEGER NOT NULL,
+    face INTEGER NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, sides, face)
+) WITHOUT ROWID;
+
+COMMIT;
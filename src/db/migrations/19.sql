@@ -0,0 +1,16 @@
+BEGIN;
+
+CREATE TABLE pin_expiry (
+    channel_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    expire_at TEXT NOT NULL,
+    PRIMARY KEY (channel_id, name)
+) WITHOUT ROWID;
+
+INSERT INTO pin_expiry (channel_id, name, expire_at)
+SELECT channel_id, name, expire_at FROM pin_schedule
+WHERE publish_at IS NULL AND expire_at IS NOT NULL;
+
+DELETE FROM pin_schedule WHERE publish_at IS NULL;
+
+COMMIT;
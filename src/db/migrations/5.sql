@@ -0,0 +1,10 @@
+BEGIN;
+
+CREATE TABLE member_joins (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    guild_id TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, guild_id, time)
+) WITHOUT ROWID;
+
+COMMIT;
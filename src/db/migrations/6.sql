@@ -0,0 +1,13 @@
+BEGIN;
+
+CREATE TABLE moderation_actions (
+    id INTEGER PRIMARY KEY NOT NULL,
+    guild_id TEXT NOT NULL,
+    moderator_id TEXT NOT NULL,
+    target_id TEXT DEFAULT NULL,
+    action TEXT NOT NULL,
+    reason TEXT DEFAULT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+COMMIT;
@@ -0,0 +1,19 @@
+BEGIN;
+
+CREATE TABLE modmail_tickets (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    open BOOLEAN NOT NULL DEFAULT TRUE,
+    opened TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed TEXT DEFAULT NULL
+);
+
+CREATE TABLE modmail_messages (
+    ticket_id INTEGER NOT NULL REFERENCES modmail_tickets (id) ON DELETE CASCADE,
+    author_id TEXT NOT NULL,
+    staff BOOLEAN NOT NULL,
+    content TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+COMMIT;
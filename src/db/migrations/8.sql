@@ -0,0 +1,11 @@
+BEGIN;
+
+CREATE TABLE rank_reactions (
+    message_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    emoji TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    PRIMARY KEY (message_id, emoji)
+) WITHOUT ROWID;
+
+COMMIT;
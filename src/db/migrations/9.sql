@@ -0,0 +1,10 @@
+BEGIN;
+
+CREATE TABLE rank_changes (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role_id TEXT NOT NULL,
+    time TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, role_id)
+) WITHOUT ROWID;
+
+COMMIT;
@@ -0,0 +1,170 @@
+use super::Result;
+use rusqlite::{named_params, types::Value, Connection, NO_PARAMS};
+use serenity::model::prelude::*;
+use std::rc::Rc;
+
+pub fn add_feed(
+    conn: &Connection,
+    name: &str,
+    source: Option<&str>,
+    query: &str,
+    channel: ChannelId,
+    author: UserId,
+) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            INSERT OR IGNORE INTO feeds (name, source, query, channel_id, author_id)
+            VALUES (:name, :source, :query, :channel_id, :author_id)
+            ",
+        )?
+        .execute_named(named_params! {
+            ":name": name,
+            ":source": source,
+            ":query": query,
+            ":channel_id": channel.to_string(),
+            ":author_id": author.to_string(),
+        })?
+        > 0)
+}
+
+pub fn delete_feed(conn: &Connection, name: &str) -> Result<bool> {
+    conn.prepare_cached(
+        "
+        DELETE FROM feed_seen
+        WHERE feed = :name
+        ",
+    )?
+    .execute_named(named_params! {
+        ":name": name,
+    })?;
+
+    Ok(conn
+        .prepare_cached(
+            "
+            DELETE FROM feeds
+            WHERE name = :name
+            ",
+        )?
+        .execute_named(named_params! {
+            ":name": name,
+        })?
+        > 0)
+}
+
+#[derive(Debug)]
+pub struct StoredFeed {
+    pub name: String,
+    pub source: Option<String>,
+    pub query: String,
+    pub channel: ChannelId,
+}
+
+pub fn get_feeds(conn: &Connection) -> Result<Vec<StoredFeed>> {
+    let feeds: rusqlite::Result<Vec<Option<StoredFeed>>> = conn
+        .prepare_cached(
+            "
+            SELECT name, source, query, channel_id FROM feeds
+            ORDER BY name
+            ",
+        )?
+        .query_map(NO_PARAMS, |row| {
+            let (name, source, query) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            let channel_id: String = row.get(3)?;
+            Ok(channel_id.parse().ok().map(|channel_id| StoredFeed {
+                name,
+                source,
+                query,
+                channel: ChannelId(channel_id),
+            }))
+        })?
+        .collect();
+
+    Ok(feeds?.into_iter().flatten().collect())
+}
+
+pub fn feed_seen(conn: &Connection, feed: &str, id: u32) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR IGNORE INTO feed_seen (feed, id)
+        VALUES (:feed, :id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":feed": feed,
+        ":id": id,
+    })?;
+
+    Ok(())
+}
+
+// keeps only the most recently seen images of a feed
+pub fn trim_feed_seen(conn: &Connection, feed: &str, history: u32) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM feed_seen
+        WHERE feed = :feed AND id NOT IN (
+            SELECT id FROM feed_seen
+            WHERE feed = :feed
+            ORDER BY time DESC, id DESC
+            LIMIT :history
+        )
+        ",
+    )?
+    .execute_named(named_params! {
+        ":feed": feed,
+        ":history": history,
+    })?;
+
+    Ok(())
+}
+
+// forgets the history of feeds that have been removed from the config
+pub fn prune_feed_seen(conn: &Connection, feeds: impl IntoIterator<Item = String>) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM feed_seen
+        WHERE feed NOT IN (SELECT value FROM rarray(:feeds))
+        ",
+    )?
+    .execute_named(named_params! {
+        ":feeds": Rc::new(feeds.into_iter().map(Value::from).collect::<Vec<_>>()),
+    })?;
+
+    Ok(())
+}
+
+pub fn feed_is_seen(conn: &Connection, feed: &str, id: u32) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT COUNT(*) FROM feed_seen
+            WHERE feed = :feed AND id = :id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":feed": feed,
+                ":id": id,
+            },
+            |row| row.get::<_, i64>(0),
+        )?
+        > 0)
+}
+
+pub fn feed_has_history(conn: &Connection, feed: &str) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT COUNT(*) FROM feed_seen
+            WHERE feed = :feed
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":feed": feed,
+            },
+            |row| row.get::<_, i64>(0),
+        )?
+        > 0)
+}
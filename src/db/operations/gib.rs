@@ -1,47 +1,179 @@
 use super::Result;
 use crate::CONFIG;
-use rusqlite::{named_params, Connection};
+use rusqlite::{named_params, Connection, OptionalExtension, NO_PARAMS};
+use serenity::model::prelude::*;
 
-pub fn gib_seen(conn: &Connection, id: u32) -> Result<()> {
+pub fn gib_seen(conn: &Connection, source: &str, scope: &str, id: u32) -> Result<()> {
     conn.prepare_cached(
         "
-        INSERT OR REPLACE INTO gib_seen (id)
-        VALUES (:id)
+        INSERT OR REPLACE INTO gib_seen (source, scope, id)
+        VALUES (:source, :scope, :id)
         ",
     )?
     .execute_named(named_params! {
+        ":source": source,
+        ":scope": scope,
         ":id": id,
     })?;
 
     conn.prepare_cached(
         "
         DELETE FROM gib_seen
-        WHERE id NOT IN (
+        WHERE source = :source AND scope = :scope AND id NOT IN (
             SELECT id FROM gib_seen
+            WHERE source = :source AND scope = :scope
             ORDER BY time DESC
             LIMIT :history
         )
         ",
     )?
     .execute_named(named_params! {
+        ":source": source,
+        ":scope": scope,
         ":history": CONFIG.gib.history,
     })?;
 
     Ok(())
 }
 
-pub fn gib_is_seen(conn: &Connection, id: u32) -> Result<bool> {
+pub fn gib_is_seen(conn: &Connection, source: &str, scope: &str, id: u32) -> Result<bool> {
     Ok(conn
         .prepare_cached(
             "
             SELECT id FROM gib_seen
-            WHERE id = :id
+            WHERE source = :source AND scope = :scope AND id = :id
             LIMIT 1
             ",
         )?
         .query_named(named_params! {
+            ":source": source,
+            ":scope": scope,
             ":id": id,
         })?
         .next()?
         .is_some())
 }
+
+pub fn gib_served(conn: &Connection, source: &str, query: &str) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT INTO gib_queries (source, query, count)
+        VALUES (:source, :query, 1)
+        ON CONFLICT (source, query)
+        DO UPDATE SET
+            count = count + 1,
+            last_served = datetime('now')
+        ",
+    )?
+    .execute_named(named_params! {
+        ":source": source,
+        ":query": query,
+    })?;
+
+    Ok(())
+}
+
+pub fn get_gib_total(conn: &Connection) -> Result<i64> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT IFNULL(SUM(count), 0) FROM gib_queries
+            ",
+        )?
+        .query_row(NO_PARAMS, |row| row.get(0))?)
+}
+
+pub fn get_gib_queries(conn: &Connection, limit: u32) -> Result<Vec<(String, String, i64)>> {
+    let queries: rusqlite::Result<Vec<(String, String, i64)>> = conn
+        .prepare_cached(
+            "
+            SELECT source, query, count FROM gib_queries
+            ORDER BY count DESC, last_served DESC
+            LIMIT :limit
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":limit": limit,
+            },
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .collect();
+
+    Ok(queries?)
+}
+
+pub fn gib_alias(
+    conn: &Connection,
+    source: &str,
+    alias: &str,
+    tag: &str,
+    author: UserId,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO gib_aliases (source, alias, tag, author_id)
+        VALUES (:source, :alias, :tag, :author_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":source": source,
+        ":alias": alias,
+        ":tag": tag,
+        ":author_id": author.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn delete_gib_alias(conn: &Connection, source: &str, alias: &str) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            DELETE FROM gib_aliases
+            WHERE source = :source AND alias = :alias
+            ",
+        )?
+        .execute_named(named_params! {
+            ":source": source,
+            ":alias": alias,
+        })?
+        > 0)
+}
+
+pub fn get_gib_alias(conn: &Connection, source: &str, alias: &str) -> Result<Option<String>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT tag FROM gib_aliases
+            WHERE source = :source AND alias = :alias
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":source": source,
+                ":alias": alias,
+            },
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn get_gib_aliases(conn: &Connection, source: &str) -> Result<Vec<(String, String)>> {
+    let aliases: rusqlite::Result<Vec<(String, String)>> = conn
+        .prepare_cached(
+            "
+            SELECT alias, tag FROM gib_aliases
+            WHERE source = :source
+            ORDER BY tag, alias
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":source": source,
+            },
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?
+        .collect();
+    Ok(aliases?)
+}
@@ -0,0 +1,35 @@
+use super::Result;
+use rusqlite::{named_params, Connection};
+use serenity::model::prelude::*;
+
+pub fn member_joined(conn: &Connection, guild: GuildId, user: UserId) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR IGNORE INTO member_joins (user_id, guild_id)
+        VALUES (:user_id, :guild_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":user_id": user.to_string(),
+        ":guild_id": guild.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn get_join_count(conn: &Connection, guild: GuildId, user: UserId) -> Result<u32> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT COUNT(*) FROM member_joins
+            WHERE user_id = :user_id AND guild_id = :guild_id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":user_id": user.to_string(),
+                ":guild_id": guild.to_string(),
+            },
+            |row| row.get(0),
+        )?)
+}
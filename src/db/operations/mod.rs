@@ -1,13 +1,31 @@
+mod feeds;
 mod gib;
+mod joins;
 mod message_cache;
+mod moderation;
+mod modmail;
+mod pins;
+mod rank_changes;
+mod rank_reactions;
 mod reddit;
+mod role_snapshots;
+mod rolls;
 mod stats;
 mod sticky_roles;
 
 use super::Result;
 
+pub use feeds::*;
 pub use gib::*;
+pub use joins::*;
 pub use message_cache::*;
+pub use moderation::*;
+pub use modmail::*;
+pub use pins::*;
+pub use rank_changes::*;
+pub use rank_reactions::*;
 pub use reddit::*;
+pub use role_snapshots::*;
+pub use rolls::*;
 pub use stats::*;
 pub use sticky_roles::*;
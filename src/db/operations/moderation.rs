@@ -0,0 +1,28 @@
+use super::Result;
+use rusqlite::{named_params, Connection};
+use serenity::model::prelude::*;
+
+pub fn moderation_action(
+    conn: &Connection,
+    guild: GuildId,
+    moderator: UserId,
+    target: Option<UserId>,
+    action: &str,
+    reason: Option<&str>,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT INTO moderation_actions (guild_id, moderator_id, target_id, action, reason)
+        VALUES (:guild_id, :moderator_id, :target_id, :action, :reason)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":guild_id": guild.to_string(),
+        ":moderator_id": moderator.to_string(),
+        ":target_id": target.map(|id| id.to_string()),
+        ":action": action,
+        ":reason": reason,
+    })?;
+
+    Ok(())
+}
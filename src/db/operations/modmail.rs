@@ -0,0 +1,127 @@
+use super::Result;
+use rusqlite::{named_params, Connection, OptionalExtension, NO_PARAMS};
+use serenity::model::prelude::*;
+
+pub fn get_open_ticket(conn: &Connection, user: UserId) -> Result<Option<i64>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT id FROM modmail_tickets
+            WHERE user_id = :user_id AND open
+            ORDER BY id DESC
+            LIMIT 1
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":user_id": user.to_string(),
+            },
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn open_ticket(conn: &Connection, user: UserId) -> Result<i64> {
+    // the user may never have been seen in a guild
+    conn.prepare_cached(
+        "
+        INSERT OR IGNORE INTO users (id)
+        VALUES (:user_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":user_id": user.to_string(),
+    })?;
+
+    conn.prepare_cached(
+        "
+        INSERT INTO modmail_tickets (user_id)
+        VALUES (:user_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":user_id": user.to_string(),
+    })?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn close_ticket(conn: &Connection, ticket: i64) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            UPDATE modmail_tickets SET
+                open = FALSE,
+                closed = datetime('now')
+            WHERE id = :id AND open
+            ",
+        )?
+        .execute_named(named_params! {
+            ":id": ticket,
+        })?
+        > 0)
+}
+
+pub fn get_ticket_user(conn: &Connection, ticket: i64) -> Result<Option<UserId>> {
+    let id: Option<String> = conn
+        .prepare_cached(
+            "
+            SELECT user_id FROM modmail_tickets
+            WHERE id = :id AND open
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":id": ticket,
+            },
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id.and_then(|id| id.parse().ok().map(UserId)))
+}
+
+pub fn get_open_tickets(conn: &Connection) -> Result<Vec<(i64, UserId, String)>> {
+    let tickets: rusqlite::Result<Vec<(i64, String, String)>> = conn
+        .prepare_cached(
+            "
+            SELECT id, user_id, opened FROM modmail_tickets
+            WHERE open
+            ORDER BY id
+            ",
+        )?
+        .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect();
+
+    Ok(tickets?
+        .into_iter()
+        .filter_map(|(id, user_id, opened)| {
+            user_id
+                .parse()
+                .ok()
+                .map(|user_id| (id, UserId(user_id), opened))
+        })
+        .collect())
+}
+
+pub fn modmail_message(
+    conn: &Connection,
+    ticket: i64,
+    author: UserId,
+    staff: bool,
+    content: &str,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT INTO modmail_messages (ticket_id, author_id, staff, content)
+        VALUES (:ticket_id, :author_id, :staff, :content)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":ticket_id": ticket,
+        ":author_id": author.to_string(),
+        ":staff": staff,
+        ":content": content,
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,363 @@
+use super::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{named_params, Connection, OptionalExtension, NO_PARAMS};
+use serenity::model::prelude::*;
+
+// matches sqlite's datetime() so schedule times compare correctly
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn get_pin_message(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+) -> Result<Option<MessageId>> {
+    let id: Option<String> = conn
+        .prepare_cached(
+            "
+            SELECT message_id FROM pins
+            WHERE channel_id = :channel_id AND name = :name
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":channel_id": channel.to_string(),
+                ":name": name,
+            },
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id.and_then(|id| id.parse().ok().map(MessageId)))
+}
+
+pub fn set_pin_message(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    message: MessageId,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO pins (channel_id, name, message_id)
+        VALUES (:channel_id, :name, :message_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+        ":message_id": message.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn get_pins(conn: &Connection, channel: ChannelId) -> Result<Vec<(String, MessageId)>> {
+    let pins: rusqlite::Result<Vec<(String, String)>> = conn
+        .prepare_cached(
+            "
+            SELECT name, message_id FROM pins
+            WHERE channel_id = :channel_id
+            ORDER BY name
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":channel_id": channel.to_string(),
+            },
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?
+        .collect();
+
+    Ok(pins?
+        .into_iter()
+        .filter_map(|(name, message_id)| {
+            message_id
+                .parse()
+                .ok()
+                .map(|message_id| (name, MessageId(message_id)))
+        })
+        .collect())
+}
+
+pub fn pin_revision(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    author: UserId,
+    content: &str,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT INTO pin_revisions (channel_id, name, author_id, content)
+        VALUES (:channel_id, :name, :author_id, :content)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+        ":author_id": author.to_string(),
+        ":content": content,
+    })?;
+
+    Ok(())
+}
+
+pub fn get_pin_revisions(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    limit: u32,
+) -> Result<Vec<(UserId, String, String)>> {
+    let revisions: rusqlite::Result<Vec<(String, String, String)>> = conn
+        .prepare_cached(
+            "
+            SELECT author_id, content, time FROM pin_revisions
+            WHERE channel_id = :channel_id AND name = :name
+            ORDER BY id DESC
+            LIMIT :limit
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":channel_id": channel.to_string(),
+                ":name": name,
+                ":limit": limit,
+            },
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .collect();
+
+    Ok(revisions?
+        .into_iter()
+        .filter_map(|(author_id, content, time)| {
+            author_id
+                .parse()
+                .ok()
+                .map(|author_id| (UserId(author_id), content, time))
+        })
+        .collect())
+}
+
+pub fn get_pin_revision(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    back: u32,
+) -> Result<Option<String>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT content FROM pin_revisions
+            WHERE channel_id = :channel_id AND name = :name
+            ORDER BY id DESC
+            LIMIT 1 OFFSET :back
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":channel_id": channel.to_string(),
+                ":name": name,
+                ":back": back,
+            },
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn delete_pin(conn: &Connection, channel: ChannelId, name: &str) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM pins
+        WHERE channel_id = :channel_id AND name = :name
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+    })?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct ScheduledPin {
+    pub id: i64,
+    pub channel_id: ChannelId,
+    pub name: String,
+    pub author_id: UserId,
+    pub content: String,
+}
+
+// replaces any pending publication of the same pin
+pub fn schedule_pin(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    author: UserId,
+    content: &str,
+    publish_at: DateTime<Utc>,
+    expire_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO pin_schedule (channel_id, name, author_id, content, publish_at, expire_at)
+        VALUES (:channel_id, :name, :author_id, :content, :publish_at, :expire_at)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+        ":author_id": author.to_string(),
+        ":content": content,
+        ":publish_at": publish_at.format(TIME_FORMAT).to_string(),
+        ":expire_at": expire_at.map(|time| time.format(TIME_FORMAT).to_string()),
+    })?;
+
+    Ok(())
+}
+
+pub fn unschedule_pin(conn: &Connection, channel: ChannelId, name: &str) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM pin_schedule
+        WHERE channel_id = :channel_id AND name = :name
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+    })?;
+
+    Ok(())
+}
+
+pub fn get_due_pins(conn: &Connection) -> Result<Vec<ScheduledPin>> {
+    let pins: rusqlite::Result<Vec<Option<ScheduledPin>>> = conn
+        .prepare_cached(
+            "
+            SELECT id, channel_id, name, author_id, content FROM pin_schedule
+            WHERE publish_at <= datetime('now')
+            ORDER BY publish_at
+            ",
+        )?
+        .query_map(NO_PARAMS, |row| {
+            let channel_id: String = row.get(1)?;
+            let author_id: String = row.get(3)?;
+            Ok(match (channel_id.parse(), author_id.parse()) {
+                (Ok(channel_id), Ok(author_id)) => Some(ScheduledPin {
+                    id: row.get(0)?,
+                    channel_id: ChannelId(channel_id),
+                    name: row.get(2)?,
+                    author_id: UserId(author_id),
+                    content: row.get(4)?,
+                }),
+                _ => None,
+            })
+        })?
+        .collect();
+
+    Ok(pins?.into_iter().flatten().collect())
+}
+
+// moves the scheduled expiry of a published pin over to pin_expiry,
+// a pin published without one replaces the expiry of the pin before it
+pub fn pin_published(conn: &Connection, id: i64) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM pin_expiry
+        WHERE EXISTS (
+            SELECT 1 FROM pin_schedule
+            WHERE id = :id
+                AND pin_schedule.channel_id = pin_expiry.channel_id
+                AND pin_schedule.name = pin_expiry.name
+        )
+        ",
+    )?
+    .execute_named(named_params! {
+        ":id": id,
+    })?;
+
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO pin_expiry (channel_id, name, expire_at)
+        SELECT channel_id, name, expire_at FROM pin_schedule
+        WHERE id = :id AND expire_at IS NOT NULL
+        ",
+    )?
+    .execute_named(named_params! {
+        ":id": id,
+    })?;
+    delete_pin_schedule(conn, id)
+}
+
+pub fn delete_pin_schedule(conn: &Connection, id: i64) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM pin_schedule
+        WHERE id = :id
+        ",
+    )?
+    .execute_named(named_params! {
+        ":id": id,
+    })?;
+
+    Ok(())
+}
+
+pub fn expire_pin(
+    conn: &Connection,
+    channel: ChannelId,
+    name: &str,
+    expire_at: DateTime<Utc>,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO pin_expiry (channel_id, name, expire_at)
+        VALUES (:channel_id, :name, :expire_at)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+        ":expire_at": expire_at.format(TIME_FORMAT).to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn unexpire_pin(conn: &Connection, channel: ChannelId, name: &str) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM pin_expiry
+        WHERE channel_id = :channel_id AND name = :name
+        ",
+    )?
+    .execute_named(named_params! {
+        ":channel_id": channel.to_string(),
+        ":name": name,
+    })?;
+
+    Ok(())
+}
+
+pub fn get_expired_pins(conn: &Connection) -> Result<Vec<(ChannelId, String)>> {
+    let pins: rusqlite::Result<Vec<(String, String)>> = conn
+        .prepare_cached(
+            "
+            SELECT channel_id, name FROM pin_expiry
+            WHERE expire_at <= datetime('now')
+            ORDER BY expire_at
+            ",
+        )?
+        .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+
+    Ok(pins?
+        .into_iter()
+        .filter_map(|(channel_id, name)| {
+            channel_id
+                .parse()
+                .ok()
+                .map(|channel_id| (ChannelId(channel_id), name))
+        })
+        .collect())
+}
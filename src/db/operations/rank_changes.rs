@@ -0,0 +1,41 @@
+use super::Result;
+use chrono::NaiveDateTime;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use serenity::model::prelude::*;
+
+pub fn rank_changed(conn: &Connection, user: UserId, role: RoleId) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO rank_changes (user_id, role_id)
+        VALUES (:user_id, :role_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":user_id": user.to_string(),
+        ":role_id": role.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn get_rank_change(
+    conn: &Connection,
+    user: UserId,
+    role: RoleId,
+) -> Result<Option<NaiveDateTime>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT time FROM rank_changes
+            WHERE user_id = :user_id AND role_id = :role_id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":user_id": user.to_string(),
+                ":role_id": role.to_string(),
+            },
+            |row| row.get(0),
+        )
+        .optional()?)
+}
@@ -0,0 +1,63 @@
+use super::Result;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use serenity::model::prelude::*;
+
+pub fn add_rank_reaction(
+    conn: &Connection,
+    channel: ChannelId,
+    message: MessageId,
+    emoji: &str,
+    role: RoleId,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO rank_reactions (message_id, channel_id, emoji, role_id)
+        VALUES (:message_id, :channel_id, :emoji, :role_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":message_id": message.to_string(),
+        ":channel_id": channel.to_string(),
+        ":emoji": emoji,
+        ":role_id": role.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn get_rank_reaction(
+    conn: &Connection,
+    message: MessageId,
+    emoji: &str,
+) -> Result<Option<RoleId>> {
+    let id: Option<String> = conn
+        .prepare_cached(
+            "
+            SELECT role_id FROM rank_reactions
+            WHERE message_id = :message_id AND emoji = :emoji
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":message_id": message.to_string(),
+                ":emoji": emoji,
+            },
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id.and_then(|id| id.parse().ok().map(RoleId)))
+}
+
+pub fn delete_rank_reactions(conn: &Connection, message: MessageId) -> Result<()> {
+    conn.prepare_cached(
+        "
+        DELETE FROM rank_reactions
+        WHERE message_id = :message_id
+        ",
+    )?
+    .execute_named(named_params! {
+        ":message_id": message.to_string(),
+    })?;
+
+    Ok(())
+}
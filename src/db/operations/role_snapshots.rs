@@ -0,0 +1,38 @@
+use super::Result;
+use rusqlite::{named_params, Connection};
+use serenity::model::prelude::*;
+
+pub fn role_snapshot(conn: &Connection, role: RoleId, count: i64) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO role_snapshots (role_id, date, count)
+        VALUES (:role_id, date('now'), :count)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":role_id": role.to_string(),
+        ":count": count,
+    })?;
+
+    Ok(())
+}
+
+pub fn get_role_snapshots(conn: &Connection, role: RoleId, days: u32) -> Result<Vec<i64>> {
+    let counts: rusqlite::Result<Vec<i64>> = conn
+        .prepare_cached(
+            "
+            SELECT count FROM role_snapshots
+            WHERE role_id = :role_id AND date > date('now', '-' || :days || ' days')
+            ORDER BY date
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":role_id": role.to_string(),
+                ":days": days,
+            },
+            |row| row.get(0),
+        )?
+        .collect();
+    Ok(counts?)
+}
@@ -0,0 +1,208 @@
+use super::Result;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use serenity::model::prelude::*;
+
+// macros are owned by either a user or a guild, whose IDs can't collide
+pub fn set_roll_macro(
+    conn: &Connection,
+    owner: u64,
+    name: &str,
+    expression: &str,
+    author: UserId,
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT OR REPLACE INTO roll_macros (owner_id, name, expression, author_id)
+        VALUES (:owner_id, :name, :expression, :author_id)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":owner_id": owner.to_string(),
+        ":name": name,
+        ":expression": expression,
+        ":author_id": author.to_string(),
+    })?;
+
+    Ok(())
+}
+
+pub fn delete_roll_macro(conn: &Connection, owner: u64, name: &str) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            DELETE FROM roll_macros
+            WHERE owner_id = :owner_id AND name = :name
+            ",
+        )?
+        .execute_named(named_params! {
+            ":owner_id": owner.to_string(),
+            ":name": name,
+        })?
+        > 0)
+}
+
+// a user's own macro takes precedence over the guild's
+pub fn get_roll_macro(
+    conn: &Connection,
+    user: UserId,
+    guild: Option<GuildId>,
+    name: &str,
+) -> Result<Option<String>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT expression FROM roll_macros
+            WHERE name = :name AND owner_id IN (:user_id, :guild_id)
+            ORDER BY owner_id = :user_id DESC
+            LIMIT 1
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":name": name,
+                ":user_id": user.to_string(),
+                ":guild_id": guild.map(|guild| guild.to_string()),
+            },
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn get_roll_macros(
+    conn: &Connection,
+    user: UserId,
+    guild: Option<GuildId>,
+) -> Result<Vec<(String, String, bool)>> {
+    let macros: rusqlite::Result<Vec<(String, String, bool)>> = conn
+        .prepare_cached(
+            "
+            SELECT name, expression, owner_id != :user_id FROM roll_macros
+            WHERE owner_id IN (:user_id, :guild_id)
+            ORDER BY name, owner_id = :user_id DESC
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":user_id": user.to_string(),
+                ":guild_id": guild.map(|guild| guild.to_string()),
+            },
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .collect();
+    Ok(macros?)
+}
+
+// with_db only hands out a shared connection, so the transaction is managed by hand
+pub fn record_roll(
+    conn: &Connection,
+    user: UserId,
+    guild: Option<GuildId>,
+    expression: &str,
+    total: f64,
+    faces: &[(u32, u32)],
+) -> Result<()> {
+    conn.execute_batch("BEGIN")?;
+    match insert_roll(conn, user, guild, expression, total, faces) {
+        Ok(()) => Ok(conn.execute_batch("COMMIT")?),
+        Err(err) => {
+            conn.execute_batch("ROLLBACK").ok();
+            Err(err)
+        }
+    }
+}
+
+fn insert_roll(
+    conn: &Connection,
+    user: UserId,
+    guild: Option<GuildId>,
+    expression: &str,
+    total: f64,
+    faces: &[(u32, u32)],
+) -> Result<()> {
+    conn.prepare_cached(
+        "
+        INSERT INTO rolls (user_id, guild_id, expression, total)
+        VALUES (:user_id, :guild_id, :expression, :total)
+        ",
+    )?
+    .execute_named(named_params! {
+        ":user_id": user.to_string(),
+        ":guild_id": guild.map(|guild| guild.to_string()),
+        ":expression": expression,
+        ":total": total,
+    })?;
+
+    let mut statement = conn.prepare_cached(
+        "
+        INSERT INTO roll_faces (user_id, sides, face, count)
+        VALUES (:user_id, :sides, :face, 1)
+        ON CONFLICT (user_id, sides, face)
+        DO UPDATE SET count = count + 1
+        ",
+    )?;
+    for (sides, face) in faces {
+        statement.execute_named(named_params! {
+            ":user_id": user.to_string(),
+            ":sides": sides,
+            ":face": face,
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn get_roll_count(conn: &Connection, user: UserId) -> Result<i64> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT COUNT(*) FROM rolls
+            WHERE user_id = :user_id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":user_id": user.to_string(),
+            },
+            |row| row.get(0),
+        )?)
+}
+
+pub fn get_recent_rolls(conn: &Connection, user: UserId, limit: u32) -> Result<Vec<(String, f64)>> {
+    let rolls: rusqlite::Result<Vec<(String, f64)>> = conn
+        .prepare_cached(
+            "
+            SELECT expression, total FROM rolls
+            WHERE user_id = :user_id
+            ORDER BY id DESC
+            LIMIT :limit
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":user_id": user.to_string(),
+                ":limit": limit,
+            },
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?
+        .collect();
+    Ok(rolls?)
+}
+
+pub fn get_roll_faces(conn: &Connection, user: UserId) -> Result<Vec<(u32, u32, i64)>> {
+    let faces: rusqlite::Result<Vec<(u32, u32, i64)>> = conn
+        .prepare_cached(
+            "
+            SELECT sides, face, count FROM roll_faces
+            WHERE user_id = :user_id
+            ORDER BY sides, face
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":user_id": user.to_string(),
+            },
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?
+        .collect();
+    Ok(faces?)
+}
@@ -1,5 +1,6 @@
 use super::Result;
-use rusqlite::{named_params, Connection};
+use chrono::NaiveDateTime;
+use rusqlite::{named_params, Connection, OptionalExtension};
 use serenity::model::prelude::*;
 
 pub fn user_online(conn: &Connection, user: &User) -> Result<()> {
@@ -126,7 +127,10 @@ pub fn channel_exists(conn: &Connection, channel: &GuildChannel) -> Result<()> {
         INSERT INTO channels (id, guild_id, name, nsfw)
         VALUES (:id, :guild_id, :name, :nsfw)
         ON CONFLICT (id)
-        DO UPDATE SET last_exists = datetime('now')
+        DO UPDATE SET
+            name = :name,
+            nsfw = :nsfw,
+            last_exists = datetime('now')
         ",
     )?
     .execute_named(named_params! {
@@ -138,3 +142,64 @@ pub fn channel_exists(conn: &Connection, channel: &GuildChannel) -> Result<()> {
 
     Ok(())
 }
+
+pub fn is_channel_nsfw(conn: &Connection, channel: ChannelId) -> Result<bool> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT nsfw FROM channels
+            WHERE id = :id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":id": channel.to_string(),
+            },
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(false))
+}
+
+pub fn get_names(conn: &Connection, user: UserId) -> Result<Vec<String>> {
+    let names: rusqlite::Result<Vec<String>> = conn
+        .prepare_cached(
+            "
+            SELECT name FROM (
+                SELECT name || '#' || discriminator AS name, last_online FROM usernames
+                WHERE id = :id
+                UNION ALL
+                SELECT nick AS name, last_online FROM nicks
+                WHERE id = :id
+            )
+            GROUP BY name
+            ORDER BY MAX(last_online) DESC
+            ",
+        )?
+        .query_map_named(
+            named_params! {
+                ":id": user.to_string(),
+            },
+            |row| row.get(0),
+        )?
+        .collect();
+    Ok(names?)
+}
+
+pub fn get_first_message(conn: &Connection, user: UserId) -> Result<Option<NaiveDateTime>> {
+    Ok(conn
+        .prepare_cached(
+            "
+            SELECT first_message FROM users
+            WHERE id = :id
+            ",
+        )?
+        .query_row_named(
+            named_params! {
+                ":id": user.to_string(),
+            },
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten())
+}
@@ -0,0 +1,791 @@
+use error_chain::error_chain;
+use std::convert::TryFrom;
+
+pub const MAX_DICE: u64 = 100;
+pub const MAX_SIDES: u64 = 1000;
+pub const MAX_ROLLS: u32 = 1000;
+const MAX_DEPTH: usize = 16;
+
+error_chain! {
+    errors {
+        Syntax(position: usize, expected: &'static str) {
+            description("invalid dice expression")
+            display("expected {} at position {}", expected, position + 1)
+        }
+        TooManyDice {
+            description("too many dice")
+            display("I can only cast {} dice at once", MAX_DICE)
+        }
+        TooManySides {
+            description("too many sides")
+            display("dice can have at most {} sides", MAX_SIDES)
+        }
+        TooManyRolls {
+            description("too many rolls")
+            display("I got tired after rolling {} dice", MAX_ROLLS)
+        }
+        Endless {
+            description("endless rolls")
+            display("those dice would keep rolling forever")
+        }
+        DivisionByZero {
+            description("division by zero")
+            display("that would divide by zero")
+        }
+        Overflow {
+            description("overflow")
+            display("that number is too big")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Condition {
+    comparison: Comparison,
+    value: i64,
+}
+
+impl Condition {
+    fn matches(self, value: i64) -> bool {
+        match self.comparison {
+            Comparison::Less => value < self.value,
+            Comparison::LessEqual => value <= self.value,
+            Comparison::Equal => value == self.value,
+            Comparison::GreaterEqual => value >= self.value,
+            Comparison::Greater => value > self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sides {
+    Number(u32),
+    Fudge,
+}
+
+impl Sides {
+    fn faces(self) -> std::ops::RangeInclusive<i64> {
+        match self {
+            Self::Number(sides) => 1..=i64::from(sides),
+            Self::Fudge => -1..=1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Selection {
+    KeepHighest(u32),
+    KeepLowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dice {
+    count: u32,
+    sides: Sides,
+    selection: Option<Selection>,
+    explode: Option<Condition>,
+    reroll: Option<(Condition, bool)>,
+    success: Option<Condition>,
+}
+
+impl Dice {
+    fn new(count: u32, sides: Sides) -> Self {
+        Self {
+            count,
+            sides,
+            selection: None,
+            explode: None,
+            reroll: None,
+            success: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Dice(Dice),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+    Group(Box<Expr>),
+    Labeled(Box<Expr>, String),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn advance(&mut self) {
+        self.position += self.peek().map_or(0, char::len_utf8);
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.text.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, word: &str) -> bool {
+        let matches = self
+            .rest()
+            .get(..word.len())
+            .map_or(false, |start| start.eq_ignore_ascii_case(word));
+        if matches {
+            self.position += word.len();
+        }
+        matches
+    }
+
+    fn error<T>(&self, expected: &'static str) -> Result<T> {
+        Err(ErrorKind::Syntax(self.position, expected).into())
+    }
+
+    // only consumes the whitespace before an operator if there is one
+    fn operator(&mut self, operators: &[char]) -> Option<char> {
+        let start = self.position;
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if operators.contains(&c) => {
+                self.advance();
+                Some(c)
+            }
+            _ => {
+                self.position = start;
+                None
+            }
+        }
+    }
+
+    fn integer(&mut self) -> Result<Option<u64>> {
+        let digits = self
+            .rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or_else(|| self.rest().len());
+        if digits == 0 {
+            return Ok(None);
+        }
+        match self.rest()[..digits].parse() {
+            Ok(number) => {
+                self.position += digits;
+                Ok(Some(number))
+            }
+            Err(_) => Err(ErrorKind::Overflow.into()),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        let mut expr = self.term()?;
+        while let Some(operator) = self.operator(&['+', '-']) {
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.term()?));
+        }
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut expr = self.factor()?;
+        while let Some(operator) = self.operator(&['*', '/']) {
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.factor()?));
+        }
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr> {
+        self.skip_whitespace();
+        if self.peek() == Some('-') {
+            self.advance();
+            self.nested(|parser| Ok(Expr::Negate(Box::new(parser.factor()?))))
+        } else {
+            let expr = self.primary()?;
+            self.label(expr)
+        }
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expr>) -> Result<Expr> {
+        if self.depth >= MAX_DEPTH {
+            return self.error("less nesting");
+        }
+        self.depth += 1;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        if self.peek() == Some('(') {
+            self.advance();
+            return self.nested(|parser| {
+                let expr = parser.expression()?;
+                parser.skip_whitespace();
+                if parser.peek() != Some(')') {
+                    return parser.error("a closing parenthesis");
+                }
+                parser.advance();
+                Ok(Expr::Group(Box::new(expr)))
+            });
+        }
+
+        for (word, selection) in &[
+            ("advantage", Selection::KeepHighest(1)),
+            ("adv", Selection::KeepHighest(1)),
+            ("disadvantage", Selection::KeepLowest(1)),
+            ("dis", Selection::KeepLowest(1)),
+        ] {
+            let start = self.position;
+            if self.eat(word) {
+                if self.peek().map_or(false, char::is_alphanumeric) {
+                    self.position = start;
+                    continue;
+                }
+                let mut dice = Dice::new(2, Sides::Number(20));
+                dice.selection = Some(*selection);
+                return Ok(Expr::Dice(dice));
+            }
+        }
+
+        let start = self.position;
+        match self.integer()? {
+            Some(count) if self.peek() == Some('d') || self.peek() == Some('D') => self.dice(count),
+            Some(_) => {
+                if self.peek() == Some('.') {
+                    self.advance();
+                    if self.integer()?.is_none() {
+                        return self.error("digits after the decimal point");
+                    }
+                }
+                match self.text[start..self.position].parse() {
+                    Ok(number) => Ok(Expr::Number(number)),
+                    Err(_) => Err(ErrorKind::Overflow.into()),
+                }
+            }
+            None if self.peek() == Some('d') || self.peek() == Some('D') => self.dice(1),
+            None => self.error("a number, dice or an opening parenthesis"),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn dice(&mut self, count: u64) -> Result<Expr> {
+        self.advance();
+        let sides = if self.eat("%") {
+            Sides::Number(100)
+        } else if self.eat("f") {
+            Sides::Fudge
+        } else {
+            match self.integer()? {
+                Some(0) => return self.error("at least one side"),
+                Some(sides) if sides > MAX_SIDES => return Err(ErrorKind::TooManySides.into()),
+                Some(sides) => Sides::Number(sides as u32),
+                None => return self.error("the number of sides"),
+            }
+        };
+        if count > MAX_DICE {
+            return Err(ErrorKind::TooManyDice.into());
+        }
+
+        let mut dice = Dice::new(count as u32, sides);
+        loop {
+            let selection: Option<fn(u32) -> Selection> = if self.eat("kl") {
+                Some(Selection::KeepLowest)
+            } else if self.eat("kh") || self.eat("k") {
+                Some(Selection::KeepHighest)
+            } else if self.eat("dh") {
+                Some(Selection::DropHighest)
+            } else if self.eat("dl") {
+                Some(Selection::DropLowest)
+            } else {
+                None
+            };
+            if let Some(selection) = selection {
+                match self.integer()? {
+                    Some(amount) => dice.selection = Some(selection(amount.min(MAX_DICE) as u32)),
+                    None => return self.error("the number of dice to keep or drop"),
+                }
+            } else if self.eat("!") {
+                let max = *sides.faces().end();
+                dice.explode = Some(self.condition()?.unwrap_or(Condition {
+                    comparison: Comparison::Equal,
+                    value: max,
+                }));
+            } else if self.eat("r") {
+                let once = self.eat("o");
+                match self.condition()? {
+                    Some(condition) => dice.reroll = Some((condition, once)),
+                    None => return self.error("a number to reroll"),
+                }
+            } else if let Some(condition) = self.comparison()? {
+                dice.success = Some(condition);
+            } else {
+                break;
+            }
+        }
+
+        let endless = |condition: Condition| sides.faces().all(|face| condition.matches(face));
+        if dice.explode.map_or(false, endless)
+            || dice
+                .reroll
+                .map_or(false, |(condition, _once)| endless(condition))
+        {
+            return Err(ErrorKind::Endless.into());
+        }
+        Ok(Expr::Dice(dice))
+    }
+
+    // a comparison, or a bare number meaning equality
+    fn condition(&mut self) -> Result<Option<Condition>> {
+        if let Some(condition) = self.comparison()? {
+            return Ok(Some(condition));
+        }
+        Ok(self.signed()?.map(|value| Condition {
+            comparison: Comparison::Equal,
+            value,
+        }))
+    }
+
+    fn comparison(&mut self) -> Result<Option<Condition>> {
+        let comparison = if self.eat("<=") {
+            Comparison::LessEqual
+        } else if self.eat(">=") {
+            Comparison::GreaterEqual
+        } else if self.eat("<") {
+            Comparison::Less
+        } else if self.eat(">") {
+            Comparison::Greater
+        } else if self.eat("=") {
+            Comparison::Equal
+        } else {
+            return Ok(None);
+        };
+        match self.signed()? {
+            Some(value) => Ok(Some(Condition { comparison, value })),
+            None => self.error("a number to compare with"),
+        }
+    }
+
+    fn signed(&mut self) -> Result<Option<i64>> {
+        let negative = self.eat("-");
+        match self.integer()? {
+            Some(value) => {
+                let value = i64::try_from(value).map_err(|_| ErrorKind::Overflow)?;
+                Ok(Some(if negative { -value } else { value }))
+            }
+            None if negative => self.error("a number"),
+            None => Ok(None),
+        }
+    }
+
+    fn label(&mut self, expr: Expr) -> Result<Expr> {
+        let start = self.position;
+        self.skip_whitespace();
+        if self.peek() != Some('[') {
+            self.position = start;
+            return Ok(expr);
+        }
+        self.advance();
+        match self.rest().find(']') {
+            Some(end) => {
+                let label = self.rest()[..end].trim().to_owned();
+                self.position += end + 1;
+                Ok(Expr::Labeled(Box::new(expr), label))
+            }
+            None => self.error("a closing bracket"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Die {
+    value: i64,
+    discarded: bool,
+    exploded: bool,
+    success: bool,
+}
+
+struct Evaluator<R> {
+    roll: R,
+    rolls: u32,
+    details: String,
+    faces: Vec<(u32, u32)>,
+}
+
+impl<R: FnMut(u32) -> u32> Evaluator<R> {
+    fn die(&mut self, sides: Sides) -> Result<i64> {
+        self.rolls += 1;
+        if self.rolls > MAX_ROLLS {
+            return Err(ErrorKind::TooManyRolls.into());
+        }
+        Ok(match sides {
+            Sides::Number(sides) => {
+                let face = (self.roll)(sides);
+                self.faces.push((sides, face));
+                i64::from(face)
+            }
+            Sides::Fudge => i64::from((self.roll)(3)) - 2,
+        })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn dice(&mut self, dice: &Dice) -> Result<f64> {
+        let mut rolled: Vec<Die> = Vec::new();
+        for _ in 0..dice.count {
+            let mut value = self.die(dice.sides)?;
+            if let Some((condition, once)) = dice.reroll {
+                while condition.matches(value) {
+                    rolled.push(Die {
+                        value,
+                        discarded: true,
+                        ..Die::default()
+                    });
+                    value = self.die(dice.sides)?;
+                    if once {
+                        break;
+                    }
+                }
+            }
+            rolled.push(Die {
+                value,
+                ..Die::default()
+            });
+            if let Some(condition) = dice.explode {
+                while condition.matches(value) {
+                    if let Some(last) = rolled.last_mut() {
+                        last.exploded = true;
+                    }
+                    value = self.die(dice.sides)?;
+                    rolled.push(Die {
+                        value,
+                        ..Die::default()
+                    });
+                }
+            }
+        }
+
+        if let Some(selection) = dice.selection {
+            let mut kept: Vec<usize> = (0..rolled.len())
+                .filter(|index| !rolled[*index].discarded)
+                .collect();
+            kept.sort_by_key(|index| rolled[*index].value);
+            let total = kept.len();
+            let (low, high) = match selection {
+                Selection::KeepHighest(amount) => (total.saturating_sub(amount as usize), 0),
+                Selection::KeepLowest(amount) => (0, total.saturating_sub(amount as usize)),
+                Selection::DropHighest(amount) => (0, (amount as usize).min(total)),
+                Selection::DropLowest(amount) => ((amount as usize).min(total), 0),
+            };
+            for index in kept[..low].iter().chain(&kept[total - high..]) {
+                rolled[*index].discarded = true;
+            }
+        }
+
+        let mut sum = 0;
+        for die in rolled.iter_mut().filter(|die| !die.discarded) {
+            match dice.success {
+                Some(condition) if condition.matches(die.value) => {
+                    die.success = true;
+                    sum += 1;
+                }
+                Some(_) => {}
+                None => sum += die.value,
+            }
+        }
+
+        let rendered: Vec<String> = rolled
+            .iter()
+            .map(|die| {
+                let value = format!("{}{}", die.value, if die.exploded { "!" } else { "" });
+                if die.discarded {
+                    format!("~~{}~~", value)
+                } else if die.success {
+                    format!("__{}__", value)
+                } else {
+                    value
+                }
+            })
+            .collect();
+        self.details.push('[');
+        self.details.push_str(&rendered.join(", "));
+        self.details.push(']');
+        Ok(sum as f64)
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<f64> {
+        let value = match expr {
+            Expr::Number(number) => {
+                self.details.push_str(&format_number(*number));
+                *number
+            }
+            Expr::Dice(dice) => self.dice(dice)?,
+            Expr::Negate(expr) => {
+                self.details.push('-');
+                -self.evaluate(expr)?
+            }
+            Expr::Binary(left, operator, right) => {
+                let left = self.evaluate(left)?;
+                self.details.push(' ');
+                self.details.push(*operator);
+                self.details.push(' ');
+                let right = self.evaluate(right)?;
+                match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    _ if right == 0.0 => return Err(ErrorKind::DivisionByZero.into()),
+                    _ => left / right,
+                }
+            }
+            Expr::Group(expr) => {
+                self.details.push('(');
+                let value = self.evaluate(expr)?;
+                self.details.push(')');
+                value
+            }
+            Expr::Labeled(expr, label) => {
+                let value = self.evaluate(expr)?;
+                self.details.push(' ');
+                self.details.push_str(label);
+                value
+            }
+        };
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(ErrorKind::Overflow.into())
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        format!("{}", number as i64)
+    } else {
+        format!("{}", number)
+    }
+}
+
+#[derive(Debug)]
+pub struct Roll {
+    pub expression: String,
+    pub label: Option<String>,
+    pub details: String,
+    pub total: f64,
+    // every numbered die cast, including discarded ones, as (sides, face)
+    pub faces: Vec<(u32, u32)>,
+}
+
+fn parse(text: &str) -> Result<(Expr, usize, Option<String>)> {
+    let mut parser = Parser {
+        text,
+        position: 0,
+        depth: 0,
+    };
+    let expr = parser.expression()?;
+
+    let end = parser.position;
+    parser.skip_whitespace();
+    let label = match parser.peek() {
+        None => None,
+        Some(c) if parser.position > end && (c.is_alphabetic() || c == '#') => {
+            Some(parser.rest().trim_start_matches('#').trim().to_owned())
+        }
+        Some(_) => return parser.error("an operator"),
+    };
+    Ok((expr, end, label))
+}
+
+// checks that an expression could be rolled without rolling it
+pub fn validate(text: &str) -> Result<()> {
+    parse(text).map(|_| ())
+}
+
+// `roll` returns a number from 1 to the given number of sides
+pub fn roll(text: &str, roll: impl FnMut(u32) -> u32) -> Result<Roll> {
+    let (expr, end, label) = parse(text)?;
+    let mut evaluator = Evaluator {
+        roll,
+        rolls: 0,
+        details: String::new(),
+        faces: Vec::new(),
+    };
+    let total = evaluator.evaluate(&expr)?;
+    Ok(Roll {
+        expression: text[..end].trim().to_owned(),
+        label,
+        details: evaluator.details,
+        total,
+        faces: evaluator.faces,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    fn scripted(text: &str, values: &[u32]) -> Result<Roll> {
+        let mut values = values.iter().cycle();
+        roll(text, |sides| {
+            let value = *values.next().expect("no scripted values");
+            assert!((1..=sides).contains(&value), "d{} rolled {}", sides, value);
+            value
+        })
+    }
+
+    fn total(text: &str, values: &[u32]) -> f64 {
+        scripted(text, values).unwrap().total
+    }
+
+    fn details(text: &str, values: &[u32]) -> String {
+        scripted(text, values).unwrap().details
+    }
+
+    fn error(text: &str, values: &[u32]) -> ErrorKind {
+        match scripted(text, values) {
+            Ok(roll) => panic!("{} rolled {:?}", text, roll),
+            Err(Error(kind, _)) => kind,
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(total("1 + 2 * 3", &[1]), 7.0);
+        assert_eq!(total("(1 + 2) * 3", &[1]), 9.0);
+        assert_eq!(total("-2 + 5", &[1]), 3.0);
+        assert_eq!(total("7 / 2", &[1]), 3.5);
+        assert_eq!(total("1.25 * 4", &[1]), 5.0);
+        assert_eq!(details("(1+2)*-3", &[1]), "(1 + 2) * -3");
+    }
+
+    #[test]
+    fn plain_dice() {
+        assert_eq!(total("2d6 + 3", &[4, 5]), 12.0);
+        assert_eq!(details("2d6 + 3", &[4, 5]), "[4, 5] + 3");
+        assert_eq!(total("d20", &[17]), 17.0);
+        assert_eq!(total("3D8", &[8]), 24.0);
+        assert_eq!(total("d%", &[99]), 99.0);
+        assert_eq!(total("0d6", &[1]), 0.0);
+    }
+
+    #[test]
+    fn keep_and_drop() {
+        assert_eq!(total("4d6kh3", &[3, 6, 1, 5]), 14.0);
+        assert_eq!(details("4d6kh3", &[3, 6, 1, 5]), "[3, 6, ~~1~~, 5]");
+        assert_eq!(total("4d6k3", &[3, 6, 1, 5]), 14.0);
+        assert_eq!(total("4d6dl1", &[3, 6, 1, 5]), 14.0);
+        assert_eq!(total("4d6kl1", &[3, 6, 1, 5]), 1.0);
+        assert_eq!(total("4d6dh1", &[3, 6, 1, 5]), 9.0);
+        assert_eq!(total("2d6kh5", &[3, 4]), 7.0);
+        assert_eq!(total("2d6dl5", &[3, 4]), 0.0);
+    }
+
+    #[test]
+    fn advantage() {
+        assert_eq!(total("adv", &[7, 15]), 15.0);
+        assert_eq!(total("dis + 2", &[7, 15]), 9.0);
+        assert_eq!(details("advantage", &[7, 15]), "[~~7~~, 15]");
+        assert_eq!(total("disadvantage", &[7, 15]), 7.0);
+    }
+
+    #[test]
+    fn exploding_dice() {
+        assert_eq!(total("d6!", &[6, 6, 2]), 14.0);
+        assert_eq!(details("d6!", &[6, 6, 2]), "[6!, 6!, 2]");
+        assert_eq!(total("2d6!>=5", &[5, 1, 4]), 10.0);
+    }
+
+    #[test]
+    fn rerolls() {
+        assert_eq!(total("2d6r<2", &[1, 1, 4, 3]), 7.0);
+        assert_eq!(details("2d6r<2", &[1, 1, 4, 3]), "[~~1~~, ~~1~~, 4, 3]");
+        assert_eq!(
+            scripted("2d6r<2 + 4dF", &[1, 1, 4, 3, 2, 2, 2, 2])
+                .unwrap()
+                .faces,
+            vec![(6, 1), (6, 1), (6, 4), (6, 3)]
+        );
+        assert_eq!(total("2d6ro1", &[1, 1, 4]), 5.0);
+        assert_eq!(total("d6r<=2kh1", &[2, 5]), 5.0);
+    }
+
+    #[test]
+    fn successes() {
+        assert_eq!(total("5d10>=7", &[7, 3, 10, 1, 8]), 3.0);
+        assert_eq!(details("3d10>7", &[7, 8, 1]), "[7, __8__, 1]");
+        assert_eq!(total("4d6=6", &[6, 6, 1, 2]), 2.0);
+    }
+
+    #[test]
+    fn fudge_dice() {
+        assert_eq!(total("4dF", &[1, 2, 3, 3]), 1.0);
+        assert_eq!(details("2df", &[1, 2]), "[-1, 0]");
+    }
+
+    #[test]
+    fn labels() {
+        let roll = scripted("2d6[fire] + 1d8 [cold] to hit", &[1, 2, 3]).unwrap();
+        assert_eq!(roll.total, 6.0);
+        assert_eq!(roll.details, "[1, 2] fire + [3] cold");
+        assert_eq!(roll.expression, "2d6[fire] + 1d8 [cold]");
+        assert_eq!(roll.label, Some("to hit".to_owned()));
+        assert_eq!(
+            scripted("d20 # initiative", &[4]).unwrap().label,
+            Some("initiative".to_owned())
+        );
+        assert_eq!(scripted("d20", &[4]).unwrap().label, None);
+        assert!(validate("8d6 fireball").is_ok());
+        assert!(validate("8d6 +").is_err());
+    }
+
+    #[test]
+    fn limits() {
+        assert!(matches!(error("101d6", &[1]), ErrorKind::TooManyDice));
+        assert!(matches!(error("d1001", &[1]), ErrorKind::TooManySides));
+        assert!(matches!(error("100d2!", &[2]), ErrorKind::TooManyRolls));
+        assert!(matches!(error("d6r<7", &[1]), ErrorKind::Endless));
+        assert!(matches!(error("d1!", &[1]), ErrorKind::Endless));
+        assert!(matches!(error("dF!>-2", &[1]), ErrorKind::Endless));
+        assert!(matches!(
+            error("1 / (2 - 2)", &[1]),
+            ErrorKind::DivisionByZero
+        ));
+        assert!(matches!(
+            error("99999999999999999999999", &[1]),
+            ErrorKind::Overflow
+        ));
+        assert!(matches!(
+            error(&format!("{}1{}", "(".repeat(50), ")".repeat(50)), &[1]),
+            ErrorKind::Syntax(_, "less nesting")
+        ));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for text in &[
+            "", "2d", "d0", "1 +", "(1", "2d6kh", "2d6r", "2d6>", "hello", "1 2", "2d6 ]", "[x]",
+            "2d6[fire",
+        ] {
+            match error(text, &[1]) {
+                ErrorKind::Syntax(..) => {}
+                other => panic!("{:?} failed with {:?}", text, other),
+            }
+        }
+    }
+}
@@ -2,17 +2,40 @@ use crate::{commands, discord_eventhandler as handler, util::can_respond_to, CON
 use log::{error, info, trace};
 use rand::{self, seq::SliceRandom};
 use serenity::{
-    framework::standard::{DispatchError, StandardFramework},
+    framework::{
+        standard::{DispatchError, Reason, StandardFramework},
+        Framework,
+    },
+    model::prelude::*,
     prelude::*,
 };
+use threadpool::ThreadPool;
+
+struct BotFramework(StandardFramework);
+
+impl Framework for BotFramework {
+    fn dispatch(&mut self, context: Context, mut message: Message, threadpool: &ThreadPool) {
+        // "gib@source tags" is parsed as "gib @source tags"
+        let gib = format!("{}gib@", CONFIG.discord.command_prefix);
+        if message.content.starts_with(&gib) {
+            message.content.insert(gib.len() - 1, ' ');
+        }
+        self.0.dispatch(context, message, threadpool);
+    }
+}
 
 #[allow(clippy::too_many_lines)]
 pub fn create_client() -> Client {
     let framework = StandardFramework::new()
-        .bucket("derp", |b| b.delay(10).time_span(10).limit(10))
+        .bucket("derp", |b| {
+            b.delay(commands::GIB_DELAY)
+                .time_span(commands::GIB_DELAY)
+                .limit(10)
+        })
         .group(&commands::HORSE_GROUP)
         .group(&commands::DISCORD_GROUP)
         .group(&commands::MISC_GROUP)
+        .group(&commands::MODERATION_GROUP)
         .help(&commands::HELP_COMMAND)
         .configure(|conf| {
             conf.owners(CONFIG.discord.owners.clone())
@@ -69,7 +92,14 @@ pub fn create_client() -> Client {
                 .ok();
         })
         .on_dispatch_error(|context, message, error| {
-            if !can_respond_to(&message) {
+            let prefix: &str = CONFIG.discord.command_prefix.as_ref();
+            let command = message
+                .content
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.split_whitespace().next())
+                .unwrap_or_default()
+                .to_lowercase();
+            if !commands::is_allowed(&message, &command) {
                 return;
             }
             let reason = match error {
@@ -92,13 +122,13 @@ pub fn create_client() -> Client {
                     max,
                     if max == 1 { "" } else { "s" }
                 ),
-                DispatchError::CheckFailed(msg, _) => msg.to_owned(),
+                DispatchError::CheckFailed(
+                    _,
+                    Reason::User(reason) | Reason::UserAndLog { user: reason, .. },
+                ) => reason,
+                DispatchError::CheckFailed(name, _) => name.to_owned(),
                 DispatchError::CommandDisabled(_) => "That command is disabled!".to_owned(),
-                DispatchError::Ratelimited(secs) => format!(
-                    "Don't spam! Try again in {} second{}...",
-                    secs,
-                    if secs == 1 { "" } else { "s" }
-                ),
+                DispatchError::Ratelimited(secs) => commands::ratelimited(secs),
                 DispatchError::BlockedUser
                 | DispatchError::BlockedGuild
                 | DispatchError::BlockedChannel
@@ -124,6 +154,6 @@ pub fn create_client() -> Client {
 
     let mut client =
         Client::new(&CONFIG.discord.token, handler::Handler).expect("Error making Discord client");
-    client.with_framework(framework);
+    client.with_framework(BotFramework(framework));
     client
 }
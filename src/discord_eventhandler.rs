@@ -1,4 +1,5 @@
-use crate::{berrytube::NowPlayingKey, db, util, CONFIG};
+use crate::{berrytube::NowPlayingKey, commands, db, modmail, raid, util, CONFIG};
+use chrono::Utc;
 use log::{info, warn};
 use rand::{self, seq::SliceRandom};
 use serenity::{model::prelude::*, prelude::*, utils::Colour};
@@ -9,6 +10,7 @@ use std::{
 };
 
 const READ_TIMEOUT: Duration = Duration::from_secs(3);
+const AUDIT_LOG_WINDOW: i64 = 30; // seconds
 
 pub fn get_log_channels(context: &Context, guild_id: GuildId) -> Vec<ChannelId> {
     CONFIG
@@ -30,10 +32,47 @@ pub fn get_log_channels(context: &Context, guild_id: GuildId) -> Vec<ChannelId>
         .collect()
 }
 
+fn find_audit_entry(
+    context: &Context,
+    guild_id: GuildId,
+    action: &ActionMember,
+    target: UserId,
+) -> Option<AuditLogEntry> {
+    let logs = match guild_id.audit_logs(context, Some(action.num()), None, None, Some(10)) {
+        Ok(logs) => logs,
+        Err(err) => {
+            warn!("Unable to read audit log: {:?}", err);
+            return None;
+        }
+    };
+    logs.entries
+        .into_iter()
+        .map(|(_id, entry)| entry)
+        .filter(|entry| {
+            entry.target_id == Some(target.0)
+                && Utc::now().signed_duration_since(entry.id.created_at())
+                    < chrono::Duration::seconds(AUDIT_LOG_WINDOW)
+        })
+        .max_by_key(|entry| entry.id.0)
+}
+
+fn describe_audit_entry(entry: &AuditLogEntry) -> String {
+    format!(
+        " by <@{}>\n{}",
+        entry.user_id,
+        entry.reason.as_ref().map_or_else(
+            || "No reason given".to_owned(),
+            |reason| format!("Reason: {}", reason)
+        )
+    )
+}
+
 pub struct Handler;
 
 impl EventHandler for Handler {
     fn ready(&self, context: Context, _: Ready) {
+        commands::spawn_pin_scheduler(context.clone());
+
         if let Some(data) = context.data.try_read_for(READ_TIMEOUT) {
             if let Some(nowplaying) = data.get::<NowPlayingKey>() {
                 context.set_presence(Some(Activity::playing(nowplaying)), OnlineStatus::Online);
@@ -78,6 +117,14 @@ impl EventHandler for Handler {
         }
     }
 
+    fn channel_update(&self, _context: Context, _old: Option<Channel>, new: Channel) {
+        if let Some(channel) = new.guild() {
+            if let Some(channel) = channel.try_read_for(READ_TIMEOUT) {
+                let _ = db::with_db(|conn| db::channel_exists(&conn, &channel));
+            }
+        }
+    }
+
     fn guild_members_chunk(
         &self,
         _context: Context,
@@ -98,6 +145,18 @@ impl EventHandler for Handler {
             db::cache_message(&conn, &message)
         });
 
+        if CONFIG.modmail.enabled
+            && message.is_private()
+            && !message.author.bot
+            && !message
+                .content
+                .starts_with(CONFIG.discord.command_prefix.as_ref() as &str)
+        {
+            if let Err(err) = modmail::handle_dm(&context, &message) {
+                warn!("Unable to forward modmail: {:?}", err);
+            }
+        }
+
         if let Some(uid) = context
             .cache
             .try_read_for(READ_TIMEOUT)
@@ -125,6 +184,8 @@ impl EventHandler for Handler {
     }
 
     fn message_delete(&self, context: Context, channel_id: ChannelId, message_id: MessageId) {
+        let _ = db::with_db(|conn| db::delete_rank_reactions(&conn, message_id));
+
         if CONFIG.discord.log_channels.contains(&channel_id) {
             return;
         }
@@ -170,16 +231,69 @@ impl EventHandler for Handler {
     }
 
     fn guild_member_addition(&self, context: Context, guild_id: GuildId, mut member: Member) {
+        // mandatory lock for sticky role restoration
+        let user_id = member.user.read().id;
         if let Some(user) = member.user.try_read_for(READ_TIMEOUT) {
             let _ = db::with_db(|conn| db::member_online(&conn, &user, &member));
         }
 
+        let previous_joins =
+            db::with_db(|conn| db::get_join_count(&conn, guild_id, user_id)).unwrap_or(0);
+        let _ = db::with_db(|conn| db::member_joined(&conn, guild_id, user_id));
+
+        let verdict = raid::check_join(&context, guild_id, user_id);
+
+        let sticky_roles =
+            db::with_db(|conn| db::get_sticky_roles(&conn, user_id)).unwrap_or_default();
+
         for log_channel in get_log_channels(&context, guild_id) {
             if let Some(user) = member.user.try_read_for(READ_TIMEOUT) {
+                let age = util::account_age(user.id);
+                let previous_names: Vec<String> = if previous_joins > 0 {
+                    db::with_db(|conn| db::get_names(&conn, user.id))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|name| *name != user.tag() && Some(name) != member.nick.as_ref())
+                        .collect()
+                } else {
+                    Vec::new()
+                };
                 if let Err(err) = log_channel.send_message(&context, |msg| {
-                    msg.embed(|e| {
+                    msg.embed(|mut e| {
+                        if previous_joins > 0 {
+                            e = e.field("Previous joins", previous_joins, true);
+                        }
+                        if !previous_names.is_empty() {
+                            e = e.field("Previous names", previous_names.join(", "), true);
+                        }
+                        if !sticky_roles.is_empty() {
+                            e = e.field(
+                                "Restoring roles",
+                                sticky_roles
+                                    .iter()
+                                    .map(|id| format!("<@&{}>", id))
+                                    .collect::<Vec<_>>()
+                                    .join(" "),
+                                true,
+                            );
+                        }
                         e.colour(Colour::FOOYOO)
-                            .description(format!("**<@{}> joined**", user.id))
+                            .description(format!(
+                                "**<@{}> {}joined**{}\nAccount created {} ago{}",
+                                user.id,
+                                if previous_joins > 0 { "re" } else { "" },
+                                if verdict.is_quarantined() {
+                                    " (quarantined)"
+                                } else {
+                                    ""
+                                },
+                                util::format_duration(age),
+                                if age.num_days() < CONFIG.discord.new_account_threshold {
+                                    " \u{26a0}\u{fe0f} **new account**"
+                                } else {
+                                    ""
+                                }
+                            ))
                             .author(|a| a.name(&user.tag()).icon_url(&user.face()))
                     })
                 }) {
@@ -188,14 +302,24 @@ impl EventHandler for Handler {
             }
         }
 
-        if let Ok(roles) = db::with_db(|conn| {
-            // mandatory lock for sticky role restoration
-            db::get_sticky_roles(&conn, member.user.read().id)
-        }) {
-            for role in roles {
-                if let Err(err) = member.add_role(&context, role) {
-                    warn!("Unable to restore a sticky role: {:?}", err);
+        for role in sticky_roles {
+            if let Err(err) = member.add_role(&context, role) {
+                warn!("Unable to restore a sticky role: {:?}", err);
+            }
+        }
+
+        match verdict {
+            raid::JoinVerdict::Normal => {}
+            raid::JoinVerdict::Quarantine => {
+                if let Err(err) = member.add_role(&context, CONFIG.raid.quarantine_role) {
+                    warn!("Unable to quarantine a new member: {:?}", err);
+                }
+            }
+            raid::JoinVerdict::Lockdown(user_ids) => {
+                for id in &user_ids {
+                    raid::quarantine(&context, guild_id, *id);
                 }
+                raid::announce_lockdown(&context, guild_id, &user_ids);
             }
         }
     }
@@ -205,13 +329,53 @@ impl EventHandler for Handler {
         context: Context,
         guild_id: GuildId,
         user: User,
-        _member: Option<Member>,
+        member: Option<Member>,
     ) {
+        // bans are logged by guild_ban_addition
+        if find_audit_entry(&context, guild_id, &ActionMember::BanAdd, user.id).is_some() {
+            return;
+        }
+        let (action, entry) = if let Some(entry) =
+            find_audit_entry(&context, guild_id, &ActionMember::Kick, user.id)
+        {
+            ("was kicked", Some(entry))
+        } else {
+            ("left", None)
+        };
+
         for log_channel in get_log_channels(&context, guild_id) {
             if let Err(err) = log_channel.send_message(&context, |msg| {
-                msg.embed(|e| {
+                msg.embed(|mut e| {
+                    if let Some(ref member) = member {
+                        if let Some(joined_at) = member.joined_at {
+                            e = e.field(
+                                "Member for",
+                                util::format_duration(Utc::now().signed_duration_since(joined_at)),
+                                true,
+                            );
+                        }
+                        if !member.roles.is_empty() {
+                            e = e.field(
+                                "Roles",
+                                member
+                                    .roles
+                                    .iter()
+                                    .map(|id| format!("<@&{}>", id))
+                                    .collect::<Vec<_>>()
+                                    .join(" "),
+                                true,
+                            );
+                        }
+                    }
                     e.colour(Colour::RED)
-                        .description(format!("**<@{}> left**", user.id))
+                        .description(format!(
+                            "**<@{}> {}**{}",
+                            user.id,
+                            action,
+                            entry
+                                .as_ref()
+                                .map_or_else(String::new, describe_audit_entry)
+                        ))
                         .author(|a| a.name(&user.tag()).icon_url(&user.face()))
                 })
             }) {
@@ -220,6 +384,48 @@ impl EventHandler for Handler {
         }
     }
 
+    fn guild_ban_addition(&self, context: Context, guild_id: GuildId, user: User) {
+        let entry = find_audit_entry(&context, guild_id, &ActionMember::BanAdd, user.id);
+        for log_channel in get_log_channels(&context, guild_id) {
+            if let Err(err) = log_channel.send_message(&context, |msg| {
+                msg.embed(|e| {
+                    e.colour(Colour::DARK_RED)
+                        .description(format!(
+                            "**<@{}> was banned**{}",
+                            user.id,
+                            entry
+                                .as_ref()
+                                .map_or_else(String::new, describe_audit_entry)
+                        ))
+                        .author(|a| a.name(&user.tag()).icon_url(&user.face()))
+                })
+            }) {
+                warn!("Unable to add ban to log channel: {:?}", err);
+            }
+        }
+    }
+
+    fn guild_ban_removal(&self, context: Context, guild_id: GuildId, user: User) {
+        let entry = find_audit_entry(&context, guild_id, &ActionMember::BanRemove, user.id);
+        for log_channel in get_log_channels(&context, guild_id) {
+            if let Err(err) = log_channel.send_message(&context, |msg| {
+                msg.embed(|e| {
+                    e.colour(Colour::DARK_GREEN)
+                        .description(format!(
+                            "**<@{}> was unbanned**{}",
+                            user.id,
+                            entry
+                                .as_ref()
+                                .map_or_else(String::new, describe_audit_entry)
+                        ))
+                        .author(|a| a.name(&user.tag()).icon_url(&user.face()))
+                })
+            }) {
+                warn!("Unable to add unban to log channel: {:?}", err);
+            }
+        }
+    }
+
     fn guild_member_update(
         &self,
         context: Context,
@@ -263,6 +469,14 @@ impl EventHandler for Handler {
         }
     }
 
+    fn reaction_add(&self, context: Context, reaction: Reaction) {
+        commands::handle_rank_reaction(&context, &reaction, true);
+    }
+
+    fn reaction_remove(&self, context: Context, reaction: Reaction) {
+        commands::handle_rank_reaction(&context, &reaction, false);
+    }
+
     fn presence_update(&self, _context: Context, update: PresenceUpdateEvent) {
         if let Some(user) = update.presence.user {
             if let Some(user) = user.try_read_for(READ_TIMEOUT) {
@@ -0,0 +1,156 @@
+use crate::{
+    booru::{self, Sort},
+    commands::image_embed,
+    db, CONFIG,
+};
+use error_chain::error_chain;
+use log::{debug, error, trace};
+use rusqlite::Connection;
+use serenity::{http::Http, model::prelude::*};
+use std::{
+    io,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAX_POSTS: usize = 10;
+// well over a page of search results, so nothing on it is forgotten and posted again
+const SEEN_HISTORY: u32 = 200;
+
+error_chain! {
+    links {
+        Database(db::Error, db::ErrorKind);
+        Booru(booru::Error, booru::ErrorKind);
+    }
+
+    foreign_links {
+        Io(::std::io::Error);
+        Discord(::serenity::Error);
+    }
+}
+
+#[derive(Debug)]
+pub struct Feed {
+    pub name: String,
+    pub source: Option<String>,
+    pub query: String,
+    pub channel: ChannelId,
+    pub configured: bool,
+}
+
+// feeds from the config take precedence over ones added with commands
+pub fn get_feeds(conn: &Connection) -> db::Result<Vec<Feed>> {
+    let mut feeds: Vec<Feed> = CONFIG
+        .feeds
+        .subscriptions
+        .iter()
+        .map(|(name, feed)| Feed {
+            name: name.to_lowercase(),
+            source: feed.source.clone(),
+            query: feed.query.clone(),
+            channel: feed.channel,
+            configured: true,
+        })
+        .collect();
+    feeds.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for stored in db::get_feeds(conn)? {
+        if !feeds.iter().any(|feed| feed.name == stored.name) {
+            feeds.push(Feed {
+                name: stored.name,
+                source: stored.source,
+                query: stored.query,
+                channel: stored.channel,
+                configured: false,
+            });
+        }
+    }
+    Ok(feeds)
+}
+
+fn check_feed(http: &Arc<Http>, database: &Connection, feed: &Feed) -> Result<()> {
+    debug!("Checking feed {}", feed.name);
+
+    let source = booru::get_source(feed.source.as_deref())?;
+    let mut query = booru::Query::parse(&feed.query)?;
+    query.sort = Sort::New;
+    let nsfw = db::is_channel_nsfw(database, feed.channel)?;
+    let response = source.search(&query, nsfw)?;
+
+    let mut unseen = Vec::new();
+    for image in &response.images {
+        if !db::feed_is_seen(database, &feed.name, image.id)? {
+            unseen.push(image);
+        }
+    }
+
+    // don't flood the channel with the backlog of a new feed
+    if !db::feed_has_history(database, &feed.name)? {
+        for image in unseen {
+            db::feed_seen(database, &feed.name, image.id)?;
+        }
+        return Ok(());
+    }
+
+    let footer = format!("{} feed on {}", feed.name, source.name());
+    for image in unseen.into_iter().take(MAX_POSTS).rev() {
+        feed.channel
+            .send_message(&http, |msg| msg.embed(|e| image_embed(e, image, &footer)))?;
+        db::feed_seen(database, &feed.name, image.id)?;
+    }
+    db::trim_feed_seen(database, &feed.name, SEEN_HISTORY)?;
+    Ok(())
+}
+
+fn main(http: &Arc<Http>) -> Result<()> {
+    let database = db::connect()?;
+    let feeds = get_feeds(&database)?;
+    db::prune_feed_seen(&database, feeds.iter().map(|feed| feed.name.clone()))?;
+    for feed in feeds {
+        if let Err(err) = check_feed(http, &database, &feed) {
+            error!("feed {} error: {:?}", feed.name, err);
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn(http: Arc<Http>) -> io::Result<thread::JoinHandle<()>> {
+    if !CONFIG.feeds.enabled {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Feed functionality is disabled in config",
+        ));
+    }
+
+    trace!("Spawning feed thread...");
+
+    let check_interval = Duration::from_secs(60 * CONFIG.feeds.check_interval);
+    if check_interval.as_secs() < 60 {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Feed check interval is less than a minute; refusing",
+        ));
+    }
+
+    thread::Builder::new()
+        .name("feeds".to_owned())
+        .spawn(move || {
+            let mut start = Instant::now();
+            loop {
+                thread::sleep(
+                    Duration::from_secs(1).max(
+                        check_interval
+                            .checked_sub(start.elapsed())
+                            .unwrap_or_default(),
+                    ),
+                );
+                if start.elapsed() >= check_interval {
+                    start = Instant::now();
+                    if let Err(err) = main(&http) {
+                        error!("feed error: {:?}", err);
+                    }
+                }
+            }
+        })
+}
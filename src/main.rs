@@ -16,12 +16,19 @@ lazy_static! {
 }
 
 mod berrytube;
+mod booru;
 mod commands;
 mod db;
+mod dice;
 mod discord;
 mod discord_eventhandler;
+mod feeds;
+mod modmail;
+mod raid;
 mod reddit;
+mod role_snapshots;
 mod serialization;
+mod textile;
 mod util;
 
 fn main() {
@@ -53,6 +60,16 @@ fn main() {
         error!("Error spawning Reddit thread: {}", err);
     }
 
+    let feed_thread = feeds::spawn(client.cache_and_http.http.clone());
+    if let Err(ref err) = feed_thread {
+        error!("Error spawning feed thread: {}", err);
+    }
+
+    let snapshot_thread = role_snapshots::spawn(client.cache_and_http.cache.clone());
+    if let Err(ref err) = snapshot_thread {
+        error!("Error spawning role snapshot thread: {}", err);
+    }
+
     if let Err(err) = client.start() {
         error!("Error running the client: {}", err);
     }
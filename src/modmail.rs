@@ -0,0 +1,157 @@
+use crate::{db, util, CONFIG};
+use error_chain::error_chain;
+use log::info;
+use serenity::{builder::CreateEmbed, model::prelude::*, prelude::*, utils::Colour};
+
+error_chain! {
+    links {
+        Database(db::Error, db::ErrorKind);
+    }
+
+    foreign_links {
+        Discord(::serenity::Error);
+    }
+
+    errors {
+        NoSuchTicket(ticket: i64) {
+            description("no such open modmail ticket")
+            display("no open modmail ticket #{}", ticket)
+        }
+    }
+}
+
+// Discord's limit on embed descriptions
+const MAX_DESCRIPTION_LENGTH: usize = 2048;
+
+#[derive(Debug, PartialEq, Eq)]
+enum TicketEvent {
+    Opened,
+    Message,
+    Closed,
+}
+
+impl TicketEvent {
+    fn title(&self, ticket: i64) -> String {
+        match *self {
+            Self::Opened => format!("New modmail ticket #{}", ticket),
+            Self::Message => format!("New message in ticket #{}", ticket),
+            Self::Closed => format!("Ticket #{} closed", ticket),
+        }
+    }
+
+    #[inline]
+    fn colour(&self) -> Colour {
+        match *self {
+            Self::Opened => Colour::RED,
+            Self::Message => Colour::ORANGE,
+            Self::Closed => Colour::DARK_GREEN,
+        }
+    }
+}
+
+fn apply_embed<'a>(
+    e: &'a mut CreateEmbed,
+    event: &TicketEvent,
+    ticket: i64,
+    user: &User,
+) -> &'a mut CreateEmbed {
+    let e = e
+        .colour(event.colour())
+        .title(event.title(ticket))
+        .author(|a| a.name(&user.tag()).icon_url(&user.face()));
+    if *event == TicketEvent::Closed {
+        e
+    } else {
+        e.footer(|f| {
+            f.text(format!(
+                "Use {0}modmail reply {1} or {0}modmail close {1} to respond",
+                CONFIG.discord.command_prefix, ticket
+            ))
+        })
+    }
+}
+
+pub fn handle_dm(context: &Context, message: &Message) -> Result<()> {
+    let mut text = message.content.clone();
+    for attachment in &message.attachments {
+        text.push('\n');
+        text.push_str(&attachment.url);
+    }
+
+    let (ticket, event) = db::with_db(|conn| {
+        let (ticket, event) = if let Some(ticket) = db::get_open_ticket(&conn, message.author.id)? {
+            (ticket, TicketEvent::Message)
+        } else {
+            (
+                db::open_ticket(&conn, message.author.id)?,
+                TicketEvent::Opened,
+            )
+        };
+        db::modmail_message(&conn, ticket, message.author.id, false, &text)?;
+        Ok((ticket, event))
+    })?;
+
+    let description = util::clip(&text, MAX_DESCRIPTION_LENGTH);
+    CONFIG.modmail.channel.send_message(context, |msg| {
+        msg.embed(|e| {
+            apply_embed(e, &event, ticket, &message.author)
+                .description(&description)
+                .timestamp(&message.timestamp)
+        })
+    })?;
+
+    if event == TicketEvent::Opened {
+        info!(
+            "Opened modmail ticket #{} for {}",
+            ticket,
+            message.author.tag()
+        );
+        message.reply(
+            context,
+            "Your message has been forwarded to the moderators. Any further messages you send here will be added to the same ticket.",
+        )?;
+    } else {
+        message.react(context, '\u{2709}')?;
+    }
+
+    Ok(())
+}
+
+pub fn reply(context: &Context, ticket: i64, moderator: UserId, text: &str) -> Result<()> {
+    let user_id = db::with_db(|conn| db::get_ticket_user(&conn, ticket))?
+        .ok_or(ErrorKind::NoSuchTicket(ticket))?;
+
+    user_id
+        .create_dm_channel(context)?
+        .send_message(context, |msg| {
+            msg.embed(|e| {
+                e.colour(Colour::BLUE)
+                    .title("Reply from the moderators")
+                    .description(util::clip(text, MAX_DESCRIPTION_LENGTH))
+            })
+        })?;
+
+    db::with_db(|conn| db::modmail_message(&conn, ticket, moderator, true, text))?;
+    Ok(())
+}
+
+pub fn close(context: &Context, ticket: i64) -> Result<()> {
+    let user_id = db::with_db(|conn| db::get_ticket_user(&conn, ticket))?
+        .ok_or(ErrorKind::NoSuchTicket(ticket))?;
+    db::with_db(|conn| db::close_ticket(&conn, ticket))?;
+
+    user_id
+        .create_dm_channel(context)?
+        .send_message(context, |msg| {
+            msg.content(
+                "Your modmail ticket has been closed. Send another message to open a new one.",
+            )
+        })?;
+
+    let user = user_id.to_user(context)?;
+    CONFIG.modmail.channel.send_message(context, |msg| {
+        msg.embed(|e| apply_embed(e, &TicketEvent::Closed, ticket, &user))
+    })?;
+
+    Ok(())
+}
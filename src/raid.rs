@@ -0,0 +1,149 @@
+use crate::{discord_eventhandler::get_log_channels, util, CONFIG};
+use log::{info, warn};
+use serenity::{model::prelude::*, prelude::*, utils::Colour};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_LISTING_LENGTH: usize = 1800;
+
+pub struct RaidKey;
+
+impl TypeMapKey for RaidKey {
+    type Value = HashMap<GuildId, RaidState>;
+}
+
+#[derive(Debug, Default)]
+pub struct RaidState {
+    joins: VecDeque<(Instant, UserId)>,
+    lockdown_until: Option<Instant>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JoinVerdict {
+    Normal,
+    Quarantine,
+    Lockdown(Vec<UserId>),
+}
+
+impl JoinVerdict {
+    #[inline]
+    pub fn is_quarantined(&self) -> bool {
+        *self != Self::Normal
+    }
+}
+
+impl RaidState {
+    fn record_join(&mut self, user_id: UserId, now: Instant) -> JoinVerdict {
+        let window = Duration::from_secs(CONFIG.raid.join_window);
+        while self
+            .joins
+            .front()
+            .map_or(false, |(time, _id)| now.duration_since(*time) > window)
+        {
+            self.joins.pop_front();
+        }
+        self.joins.push_back((now, user_id));
+
+        if self.lockdown_until.map_or(false, |until| now < until) {
+            JoinVerdict::Quarantine
+        } else if self.joins.len() >= CONFIG.raid.join_limit {
+            self.lockdown_until =
+                Some(now + Duration::from_secs(60 * CONFIG.raid.lockdown_duration));
+            JoinVerdict::Lockdown(self.joins.iter().map(|(_time, id)| *id).collect())
+        } else {
+            JoinVerdict::Normal
+        }
+    }
+}
+
+pub fn check_join(context: &Context, guild_id: GuildId, user_id: UserId) -> JoinVerdict {
+    if !CONFIG.raid.enabled {
+        return JoinVerdict::Normal;
+    }
+
+    context
+        .data
+        .try_write_for(WRITE_TIMEOUT)
+        .map_or(JoinVerdict::Normal, |mut data| {
+            data.entry::<RaidKey>()
+                .or_insert_with(HashMap::new)
+                .entry(guild_id)
+                .or_default()
+                .record_join(user_id, Instant::now())
+        })
+}
+
+pub fn quarantine(context: &Context, guild_id: GuildId, user_id: UserId) {
+    if let Err(err) = guild_id
+        .member(context, user_id)
+        .and_then(|mut member| member.add_role(context, CONFIG.raid.quarantine_role))
+    {
+        warn!("Unable to quarantine {}: {:?}", user_id, err);
+    }
+}
+
+fn describe_suspect(context: &Context, user_id: UserId) -> String {
+    let age = util::format_duration(util::account_age(user_id));
+    if let Ok(user) = user_id.to_user(context) {
+        format!(
+            "<@{}> ({}), account age {}{}",
+            user.id,
+            user.tag(),
+            age,
+            if user.avatar.is_none() {
+                ", default avatar"
+            } else {
+                ""
+            }
+        )
+    } else {
+        format!("<@{}>, account age {}", user_id, age)
+    }
+}
+
+pub fn announce_lockdown(context: &Context, guild_id: GuildId, user_ids: &[UserId]) {
+    info!(
+        "Raid detected on {}, locking down for {} minutes",
+        guild_id, CONFIG.raid.lockdown_duration
+    );
+
+    let mut listing = String::new();
+    for (index, user_id) in user_ids.iter().enumerate() {
+        let line = describe_suspect(context, *user_id);
+        if listing.len() + line.len() > MAX_LISTING_LENGTH {
+            listing.push_str(&format!("\u{2026}and {} more", user_ids.len() - index));
+            break;
+        }
+        listing.push_str(&line);
+        listing.push('\n');
+    }
+
+    let mentions: Vec<String> = CONFIG
+        .raid
+        .notify_roles
+        .iter()
+        .map(|id| format!("<@&{}>", id))
+        .collect();
+
+    for log_channel in get_log_channels(context, guild_id) {
+        if let Err(err) = log_channel.send_message(context, |msg| {
+            msg.content(mentions.join(" ")).embed(|e| {
+                e.colour(Colour::DARK_RED)
+                    .title("Raid detected, lockdown enabled")
+                    .description(format!(
+                        "**{} joins within {} seconds.** New members will get <@&{}> for the next {} minutes.\n{}",
+                        user_ids.len(),
+                        CONFIG.raid.join_window,
+                        CONFIG.raid.quarantine_role,
+                        CONFIG.raid.lockdown_duration,
+                        listing
+                    ))
+            })
+        }) {
+            warn!("Unable to add lockdown alert to log channel: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::db;
+use log::{error, trace};
+use serenity::cache::CacheRwLock;
+use std::{convert::TryFrom, io, thread, time::Duration};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn main(cache: &CacheRwLock) -> db::Result<()> {
+    let guilds: Vec<_> = cache
+        .try_read_for(READ_TIMEOUT)
+        .map(|cache| cache.guilds.values().cloned().collect())
+        .unwrap_or_default();
+
+    let database = db::connect()?;
+    for guild in guilds {
+        if let Some(guild) = guild.try_read_for(READ_TIMEOUT) {
+            for role_id in guild.roles.keys() {
+                let count = guild
+                    .members
+                    .values()
+                    .filter(|member| member.roles.contains(role_id))
+                    .count();
+                db::role_snapshot(
+                    &database,
+                    *role_id,
+                    i64::try_from(count).unwrap_or(i64::MAX),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn(cache: CacheRwLock) -> io::Result<thread::JoinHandle<()>> {
+    trace!("Spawning role snapshot thread...");
+
+    thread::Builder::new()
+        .name("role_snapshots".to_owned())
+        .spawn(move || loop {
+            thread::sleep(SNAPSHOT_INTERVAL);
+            if let Err(err) = main(&cache) {
+                error!("role snapshot error: {:?}", err);
+            }
+        })
+}
@@ -0,0 +1,516 @@
+use url::Url;
+
+// deeper openers are left as text so crafted input can't blow the stack
+const MAX_DEPTH: usize = 16;
+
+// longer delimiters first so that ** isn't read as two *
+const INLINE: &[(&str, Inline)] = &[
+    ("**", Inline::Bold),
+    ("__", Inline::Italic),
+    ("*", Inline::Bold),
+    ("_", Inline::Italic),
+    ("+", Inline::Underline),
+    ("-", Inline::Strikethrough),
+    ("^", Inline::Superscript),
+    ("~", Inline::Subscript),
+];
+
+const SUPERSCRIPT: &[(char, char)] = &[
+    ('0', '\u{2070}'),
+    ('1', '\u{00b9}'),
+    ('2', '\u{00b2}'),
+    ('3', '\u{00b3}'),
+    ('4', '\u{2074}'),
+    ('5', '\u{2075}'),
+    ('6', '\u{2076}'),
+    ('7', '\u{2077}'),
+    ('8', '\u{2078}'),
+    ('9', '\u{2079}'),
+    ('+', '\u{207a}'),
+    ('-', '\u{207b}'),
+    ('=', '\u{207c}'),
+    ('(', '\u{207d}'),
+    (')', '\u{207e}'),
+    ('i', '\u{2071}'),
+    ('n', '\u{207f}'),
+    (' ', ' '),
+];
+
+const SUBSCRIPT: &[(char, char)] = &[
+    ('0', '\u{2080}'),
+    ('1', '\u{2081}'),
+    ('2', '\u{2082}'),
+    ('3', '\u{2083}'),
+    ('4', '\u{2084}'),
+    ('5', '\u{2085}'),
+    ('6', '\u{2086}'),
+    ('7', '\u{2087}'),
+    ('8', '\u{2088}'),
+    ('9', '\u{2089}'),
+    ('+', '\u{208a}'),
+    ('-', '\u{208b}'),
+    ('=', '\u{208c}'),
+    ('(', '\u{208d}'),
+    (')', '\u{208e}'),
+    (' ', ' '),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inline {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Superscript,
+    Subscript,
+}
+
+impl Inline {
+    // x^2^ and H~2~O are written without surrounding spaces
+    #[inline]
+    fn needs_boundary(self) -> bool {
+        self != Self::Superscript && self != Self::Subscript
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Code(String),
+    Inline(Inline, Vec<Node>),
+    Spoiler(Vec<Node>),
+    Quote(Vec<Node>),
+    Link(Vec<Node>, String),
+    Image(String),
+    ImageRef(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Terminator<'a> {
+    Tag(&'a str),
+    Delimiter(&'a str),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+#[inline]
+fn is_boundary(c: Option<char>) -> bool {
+    c.map_or(true, |c| !c.is_alphanumeric())
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn prev_char(&self) -> Option<char> {
+        self.text[..self.pos].chars().next_back()
+    }
+
+    fn is_terminator(&self, terminator: Terminator<'_>) -> bool {
+        let rest = self.rest();
+        match terminator {
+            Terminator::Tag(tag) => rest.starts_with(tag),
+            Terminator::Delimiter(delimiter) => {
+                rest.starts_with(delimiter)
+                    && !self.prev_char().map_or(true, char::is_whitespace)
+                    && is_boundary(rest[delimiter.len()..].chars().next())
+            }
+        }
+    }
+
+    fn consume(&mut self, terminator: Terminator<'_>) -> bool {
+        if self.is_terminator(terminator) {
+            self.pos += match terminator {
+                Terminator::Tag(tag) => tag.len(),
+                Terminator::Delimiter(delimiter) => delimiter.len(),
+            };
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse(&mut self, terminators: &[Terminator<'_>]) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        while self.pos < self.text.len() {
+            if terminators.iter().any(|t| self.is_terminator(*t)) {
+                break;
+            }
+            if let Some(node) = self.parse_special(terminators) {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(std::mem::take(&mut text)));
+                }
+                nodes.extend(node);
+                continue;
+            }
+
+            let c = self.rest().chars().next().unwrap_or_default();
+            text.push(c);
+            self.pos += c.len_utf8();
+        }
+
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        nodes
+    }
+
+    fn parse_deeper(&mut self, terminators: &[Terminator<'_>]) -> Vec<Node> {
+        self.depth += 1;
+        let nodes = self.parse(terminators);
+        self.depth -= 1;
+        nodes
+    }
+
+    // returns several nodes when an unclosed delimiter turns back into text
+    #[allow(clippy::too_many_lines)]
+    fn parse_special(&mut self, terminators: &[Terminator<'_>]) -> Option<Vec<Node>> {
+        let rest = self.rest();
+
+        if rest.starts_with("[==") {
+            let end = rest.find("==]")?;
+            self.pos += end + 3;
+            return Some(vec![Node::Text(rest[3..end].to_owned())]);
+        }
+        let nested = self.depth < MAX_DEPTH;
+        if nested && rest.starts_with("[spoiler]") {
+            self.pos += "[spoiler]".len();
+            let children = self.parse_nested(terminators, Terminator::Tag("[/spoiler]"));
+            return Some(vec![Node::Spoiler(children)]);
+        }
+        if nested && (rest.starts_with("[bq]") || rest.starts_with("[bq=")) {
+            self.pos += rest.find(']')? + 1;
+            let children = self.parse_nested(terminators, Terminator::Tag("[/bq]"));
+            return Some(vec![Node::Quote(children)]);
+        }
+        if rest.starts_with(">>") {
+            let digits: String = rest[2..].chars().take_while(char::is_ascii_digit).collect();
+            if let Ok(id) = digits.parse() {
+                self.pos += 2 + digits.len();
+                if self.rest().starts_with(&['t', 's', 'p'][..])
+                    && is_boundary(self.rest().chars().nth(1))
+                {
+                    self.pos += 1;
+                }
+                return Some(vec![Node::ImageRef(id)]);
+            }
+        }
+
+        let at_boundary = is_boundary(self.prev_char());
+        if nested && at_boundary && rest.starts_with('"') {
+            let end = rest[1..].find("\":")? + 1;
+            let url_len = rest[end + 2..]
+                .find(|c: char| c.is_whitespace() || c == '<' || c == '>')
+                .unwrap_or(rest.len() - end - 2);
+            let url =
+                rest[end + 2..end + 2 + url_len].trim_end_matches(&['.', ',', '!', '?', ')'][..]);
+            if url.is_empty() {
+                return None;
+            }
+            let children = Parser {
+                text: &rest[1..end],
+                pos: 0,
+                depth: self.depth + 1,
+            }
+            .parse(&[]);
+            self.pos += end + 2 + url.len();
+            return Some(vec![Node::Link(children, url.to_owned())]);
+        }
+        if at_boundary && rest.starts_with('!') {
+            let end = rest[1..].find('!')? + 1;
+            let url = &rest[1..end];
+            if url.is_empty() || url.contains(char::is_whitespace) {
+                return None;
+            }
+            self.pos += end + 1;
+            // an image can also link somewhere, but the image itself is more useful
+            if self.rest().starts_with(':') {
+                self.pos += self.rest()[1..]
+                    .find(char::is_whitespace)
+                    .unwrap_or(self.rest().len() - 1)
+                    + 1;
+            }
+            return Some(vec![Node::Image(url.to_owned())]);
+        }
+        if at_boundary && rest.starts_with('@') {
+            let end = rest[1..].find('@')? + 1;
+            if end == 1 || rest[1..].starts_with(char::is_whitespace) {
+                return None;
+            }
+            self.pos += end + 1;
+            return Some(vec![Node::Code(rest[1..end].to_owned())]);
+        }
+
+        if !nested {
+            return None;
+        }
+        for (delimiter, inline) in INLINE {
+            let bracketed = rest.starts_with('[') && rest[1..].starts_with(delimiter);
+            if !bracketed
+                && !(rest.starts_with(delimiter) && (at_boundary || !inline.needs_boundary()))
+            {
+                continue;
+            }
+            let open_len = delimiter.len() + usize::from(bracketed);
+            if rest[open_len..]
+                .chars()
+                .next()
+                .map_or(true, char::is_whitespace)
+            {
+                continue;
+            }
+
+            let closing_tag;
+            let closing = if bracketed {
+                closing_tag = format!("{}]", delimiter);
+                Terminator::Tag(&closing_tag)
+            } else if inline.needs_boundary() {
+                Terminator::Delimiter(delimiter)
+            } else {
+                Terminator::Tag(delimiter)
+            };
+            let start = self.pos;
+            self.pos += open_len;
+            let children = self.parse_deeper(&[&[closing], terminators].concat());
+            return Some(if self.consume(closing) {
+                vec![Node::Inline(*inline, children)]
+            } else {
+                let mut nodes = vec![Node::Text(self.text[start..start + open_len].to_owned())];
+                nodes.extend(children);
+                nodes
+            });
+        }
+
+        None
+    }
+
+    fn parse_nested(
+        &mut self,
+        terminators: &[Terminator<'_>],
+        closing: Terminator<'_>,
+    ) -> Vec<Node> {
+        let children = self.parse_deeper(&[&[closing], terminators].concat());
+        self.consume(closing);
+        children
+    }
+}
+
+fn map_chars(text: &str, table: &[(char, char)]) -> Option<String> {
+    text.chars()
+        .map(|c| {
+            table
+                .iter()
+                .find(|(from, _to)| *from == c)
+                .map(|(_from, to)| *to)
+        })
+        .collect()
+}
+
+fn render(nodes: &[Node], base_url: &Url, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Code(code) => {
+                out.push('`');
+                out.push_str(code);
+                out.push('`');
+            }
+            Node::Inline(inline, children) => {
+                let mut inner = String::new();
+                render(children, base_url, &mut inner);
+                match inline {
+                    Inline::Bold => out.push_str(&format!("**{}**", inner)),
+                    Inline::Italic => out.push_str(&format!("*{}*", inner)),
+                    Inline::Underline => out.push_str(&format!("__{}__", inner)),
+                    Inline::Strikethrough => out.push_str(&format!("~~{}~~", inner)),
+                    Inline::Superscript => {
+                        out.push_str(&map_chars(&inner, SUPERSCRIPT).unwrap_or(inner));
+                    }
+                    Inline::Subscript => {
+                        out.push_str(&map_chars(&inner, SUBSCRIPT).unwrap_or(inner));
+                    }
+                }
+            }
+            Node::Spoiler(children) => {
+                out.push_str("||");
+                render(children, base_url, out);
+                out.push_str("||");
+            }
+            Node::Quote(children) => {
+                let mut inner = String::new();
+                render(children, base_url, &mut inner);
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                for line in inner.trim().lines() {
+                    out.push_str("> ");
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            Node::Link(children, url) => {
+                out.push('[');
+                render(children, base_url, out);
+                out.push_str("](");
+                out.push_str(url);
+                out.push(')');
+            }
+            Node::Image(url) => {
+                out.push_str("[Image](");
+                out.push_str(url);
+                out.push(')');
+            }
+            Node::ImageRef(id) => {
+                let url = base_url
+                    .join(&id.to_string())
+                    .map_or_else(|_| id.to_string(), |url| url.as_str().to_owned());
+                out.push_str(&format!("[>>{}]({})", id, url));
+            }
+        }
+    }
+}
+
+pub fn to_markdown(text: &str, base_url: &Url) -> String {
+    let text = text.replace("\r\n", "\n");
+    let nodes = Parser {
+        text: &text,
+        pos: 0,
+        depth: 0,
+    }
+    .parse(&[]);
+
+    let mut out = String::new();
+    render(&nodes, base_url, &mut out);
+    out.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(text: &str) -> String {
+        to_markdown(text, &Url::parse("https://derpibooru.org/").unwrap())
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(convert("just some words"), "just some words");
+        assert_eq!(convert("well-known 5 * 3 - 2"), "well-known 5 * 3 - 2");
+        assert_eq!(convert("snake_case_name"), "snake_case_name");
+    }
+
+    #[test]
+    fn inline_formatting() {
+        assert_eq!(convert("a *bold* word"), "a **bold** word");
+        assert_eq!(convert("a **bold** word"), "a **bold** word");
+        assert_eq!(convert("an _italic_ word"), "an *italic* word");
+        assert_eq!(convert("an +underlined+ word"), "an __underlined__ word");
+        assert_eq!(convert("a -struck- word"), "a ~~struck~~ word");
+        assert_eq!(convert("some @co*de*@ here"), "some `co*de*` here");
+        assert_eq!(convert("*bold at the edges*"), "**bold at the edges**");
+    }
+
+    #[test]
+    fn nested_formatting() {
+        assert_eq!(
+            convert("*bold _and italic_ text*"),
+            "**bold *and italic* text**"
+        );
+        assert_eq!(convert("+under -and struck-+"), "__under ~~and struck~~__");
+        assert_eq!(
+            convert("[spoiler]*hidden* thing[/spoiler]"),
+            "||**hidden** thing||"
+        );
+    }
+
+    #[test]
+    fn bracketed_formatting() {
+        assert_eq!(convert("in[*side*]word"), "in**side**word");
+        assert_eq!(convert("un[_closed"), "un[_closed");
+    }
+
+    #[test]
+    fn unclosed_delimiters_are_text() {
+        assert_eq!(convert("*not bold"), "*not bold");
+        assert_eq!(convert("*bold _not italic*"), "**bold _not italic**");
+    }
+
+    #[test]
+    fn superscript_and_subscript() {
+        assert_eq!(convert("x^2^ + y^10^"), "x\u{b2} + y\u{b9}\u{2070}");
+        assert_eq!(convert("H~2~O"), "H\u{2082}O");
+        assert_eq!(convert("a ^word^ b"), "a word b");
+    }
+
+    #[test]
+    fn spoilers() {
+        assert_eq!(convert("[spoiler]she dies[/spoiler]"), "||she dies||");
+        assert_eq!(convert("[spoiler]unclosed"), "||unclosed||");
+    }
+
+    #[test]
+    fn deep_nesting_is_text() {
+        let spoilers = "[spoiler]".repeat(10_000);
+        let converted = convert(&spoilers);
+        assert!(converted.starts_with(&"||".repeat(MAX_DEPTH)));
+        assert!(converted.contains(&"[spoiler]".repeat(100)));
+
+        let bold = "*x ".repeat(10_000);
+        assert!(convert(&bold).ends_with("*x"));
+    }
+
+    #[test]
+    fn block_quotes() {
+        assert_eq!(
+            convert("before[bq]quoted\nlines[/bq]after"),
+            "before\n> quoted\n> lines\nafter"
+        );
+        assert_eq!(convert("[bq=\"someone\"]*hi*[/bq]"), "> **hi**");
+    }
+
+    #[test]
+    fn links_and_images() {
+        assert_eq!(
+            convert("see \"my *gallery*\":https://example.com/g."),
+            "see [my **gallery**](https://example.com/g)."
+        );
+        assert_eq!(
+            convert("look !https://example.com/a.png! here"),
+            "look [Image](https://example.com/a.png) here"
+        );
+        assert_eq!(
+            convert("!https://example.com/a.png!:https://example.com"),
+            "[Image](https://example.com/a.png)"
+        );
+    }
+
+    #[test]
+    fn image_references() {
+        assert_eq!(
+            convert("sequel to >>12345"),
+            "sequel to [>>12345](https://derpibooru.org/12345)"
+        );
+        assert_eq!(
+            convert(">>678t and >>9p"),
+            "[>>678](https://derpibooru.org/678) and [>>9](https://derpibooru.org/9)"
+        );
+        assert_eq!(convert(">>abc"), ">>abc");
+    }
+
+    #[test]
+    fn no_parse() {
+        assert_eq!(convert("[==*raw* _text_==]"), "*raw* _text_");
+    }
+
+    #[test]
+    fn multibyte_text() {
+        assert_eq!(convert("*caf\u{e9}* \u{1f434}"), "**caf\u{e9}** \u{1f434}");
+    }
+}
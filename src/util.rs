@@ -1,4 +1,5 @@
 use crate::CONFIG;
+use chrono::{Duration, Utc};
 use serenity::model::prelude::*;
 
 pub fn can_talk_in(channel_id: ChannelId) -> bool {
@@ -10,3 +11,114 @@ pub fn can_talk_in(channel_id: ChannelId) -> bool {
 pub fn can_respond_to(message: &Message) -> bool {
     can_talk_in(message.channel_id)
 }
+
+pub fn account_age(user_id: UserId) -> Duration {
+    Utc::now().signed_duration_since(user_id.created_at())
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let units = [
+        ("year", duration.num_days() / 365),
+        ("day", duration.num_days() % 365),
+        ("hour", duration.num_hours() % 24),
+        ("minute", duration.num_minutes() % 60),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(_unit, amount)| *amount <= 0)
+        .take(2)
+        .filter(|(_unit, amount)| *amount > 0)
+        .map(|(unit, amount)| format!("{} {}{}", amount, unit, if *amount == 1 { "" } else { "s" }))
+        .collect();
+    if parts.is_empty() {
+        "less than a minute".to_owned()
+    } else {
+        parts.join(", ")
+    }
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(row[j]).min(above)
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+pub fn truncate(text: &str, max_length: usize) -> &str {
+    if text.len() <= max_length {
+        return text;
+    }
+    let mut end = max_length;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// cuts text down to max_length bytes including a trailing ellipsis
+pub fn clip(text: &str, max_length: usize) -> String {
+    if text.len() > max_length {
+        format!(
+            "{}\u{2026}",
+            truncate(text, max_length - '\u{2026}'.len_utf8())
+        )
+    } else {
+        text.to_owned()
+    }
+}
+
+pub fn sparkline(values: &[i64]) -> String {
+    const BARS: [char; 8] = [
+        '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}',
+        '\u{2588}',
+    ];
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|value| {
+            if max == min {
+                BARS[BARS.len() / 2]
+            } else {
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_possible_wrap,
+                    clippy::cast_sign_loss
+                )]
+                BARS[((value - min) * (BARS.len() as i64 - 1) / (max - min)) as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate("too long", 3), "too");
+        assert_eq!(truncate("caf\u{e9}", 4), "caf");
+        assert_eq!(truncate("\u{1f434}\u{1f434}", 5), "\u{1f434}");
+    }
+
+    #[test]
+    fn clipping() {
+        assert_eq!(clip("short", 10), "short");
+        assert_eq!(clip("much too long", 10), "much to\u{2026}");
+    }
+}